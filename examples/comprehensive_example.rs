@@ -1,6 +1,5 @@
 #![allow(dead_code)]
 use struct_reflection::StructReflection;
-use struct_reflection::StructReflectionHelper;
 
 // Example with primitive types and arrays
 #[derive(StructReflection)]
@@ -1,7 +1,6 @@
 #![allow(dead_code)]
 use std::marker::PhantomData;
 use struct_reflection::StructReflection;
-use struct_reflection::StructReflectionHelper;
 
 // Example with multiple generic parameters
 #[derive(StructReflection)]
@@ -1,6 +1,5 @@
 #![allow(dead_code)]
 use struct_reflection::StructReflection;
-use struct_reflection::StructReflectionHelper;
 
 // Basic tuple struct (unnamed fields)
 #[derive(StructReflection)]
@@ -0,0 +1,214 @@
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::FieldPath;
+
+/// One reflected leaf of a live value, with its path.
+#[derive(Debug, Clone)]
+pub struct FieldRef<'a> {
+    pub path: FieldPath,
+    /// The leaf itself, to be downcast to its type.
+    pub value: &'a dyn Any,
+}
+
+type FromStrFn = fn(&mut dyn Any, &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+/// One reflected leaf of a live value, mutably borrowed, with its path.
+pub struct FieldMut<'a> {
+    pub path: FieldPath,
+    /// The leaf itself, to be downcast to its type, or the `Option` holding
+    /// `None` in its place.
+    pub value: &'a mut dyn Any,
+    type_name: &'static str,
+    from_str: Option<FromStrFn>,
+    /// Whether `value` is an option holding `None`, which assigning a leaf
+    /// sets to `Some`.
+    unset: bool,
+}
+
+impl<'a> FieldMut<'a> {
+    /// A leaf that cannot be parsed from a string.
+    pub fn new<T: Any>(path: FieldPath, value: &'a mut T) -> Self {
+        FieldMut {
+            path,
+            value,
+            type_name: std::any::type_name::<T>(),
+            from_str: None,
+            unset: false,
+        }
+    }
+
+    /// A leaf that [`set_from_str`](FieldMut::set_from_str) parses with its
+    /// `FromStr` impl.
+    pub fn parsable<T>(path: FieldPath, value: &'a mut T) -> Self
+    where
+        T: Any + FromStr,
+        T::Err: Error + Send + Sync + 'static,
+    {
+        FieldMut {
+            from_str: Some(|value, s| {
+                // `value` is always the `T` it was created from
+                if let Some(value) = value.downcast_mut::<T>() {
+                    *value = s.parse()?;
+                }
+                Ok(())
+            }),
+            ..FieldMut::new(path, value)
+        }
+    }
+
+    /// The leaf of an option holding `None`, which cannot be parsed from a
+    /// string. Assigning it sets the option to `Some`.
+    pub fn unset<T: Any>(path: FieldPath, option: &'a mut Option<T>) -> Self {
+        FieldMut {
+            type_name: std::any::type_name::<T>(),
+            unset: true,
+            ..FieldMut::new(path, option)
+        }
+    }
+
+    /// The leaf of an option holding `None`, which [`set_from_str`](FieldMut::set_from_str)
+    /// sets to `Some` of the value parsed with its `FromStr` impl.
+    pub fn parsable_unset<T>(path: FieldPath, option: &'a mut Option<T>) -> Self
+    where
+        T: Any + FromStr,
+        T::Err: Error + Send + Sync + 'static,
+    {
+        FieldMut {
+            from_str: Some(|option, s| {
+                // `option` is always the `Option<T>` it was created from
+                if let Some(option) = option.downcast_mut::<Option<T>>() {
+                    *option = Some(s.parse()?);
+                }
+                Ok(())
+            }),
+            ..FieldMut::unset(path, option)
+        }
+    }
+
+    /// The leaf's type, as given by [`std::any::type_name`].
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Replaces the leaf with `value`.
+    pub fn set<T: Any>(&mut self, value: T) -> Result<(), SetFieldError> {
+        if self.unset {
+            if let Some(option) = self.value.downcast_mut::<Option<T>>() {
+                *option = Some(value);
+                return Ok(());
+            }
+        } else if let Some(leaf) = self.value.downcast_mut::<T>() {
+            *leaf = value;
+            return Ok(());
+        }
+
+        Err(SetFieldError::TypeMismatch {
+            field: self.path.to_string(),
+            expected: self.type_name,
+            found: std::any::type_name::<T>(),
+        })
+    }
+
+    /// Replaces the leaf with the value parsed from `s`.
+    pub fn set_from_str(&mut self, s: &str) -> Result<(), SetFieldError> {
+        let from_str = self.from_str.ok_or_else(|| SetFieldError::NotParsable {
+            field: self.path.to_string(),
+            type_name: self.type_name,
+        })?;
+        from_str(self.value, s).map_err(|source| SetFieldError::Parse {
+            field: self.path.to_string(),
+            type_name: self.type_name,
+            source,
+        })
+    }
+}
+
+impl fmt::Debug for FieldMut<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FieldMut")
+            .field("path", &self.path)
+            .field("type_name", &self.type_name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Error returned when assigning a field by name.
+#[derive(Debug)]
+pub enum SetFieldError {
+    /// No leaf of the value is reflected under this name.
+    UnknownField(String),
+    /// The leaf is not of the type of the value given.
+    TypeMismatch {
+        field: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The leaf's type has no `FromStr` impl that the crate knows of.
+    NotParsable {
+        field: String,
+        type_name: &'static str,
+    },
+    /// The string is not a valid value of the leaf's type.
+    Parse {
+        field: String,
+        type_name: &'static str,
+        source: Box<dyn Error + Send + Sync>,
+    },
+}
+
+impl SetFieldError {
+    /// The name of the field the error is about.
+    pub fn field(&self) -> &str {
+        match self {
+            SetFieldError::UnknownField(field)
+            | SetFieldError::TypeMismatch { field, .. }
+            | SetFieldError::NotParsable { field, .. }
+            | SetFieldError::Parse { field, .. } => field,
+        }
+    }
+
+    // Leaves only know their own path, which the caller knows a better name for
+    pub(crate) fn with_field(mut self, name: &str) -> Self {
+        match &mut self {
+            SetFieldError::UnknownField(field)
+            | SetFieldError::TypeMismatch { field, .. }
+            | SetFieldError::NotParsable { field, .. }
+            | SetFieldError::Parse { field, .. } => *field = name.to_string(),
+        }
+        self
+    }
+}
+
+impl fmt::Display for SetFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetFieldError::UnknownField(field) => write!(f, "no field named `{field}`"),
+            SetFieldError::TypeMismatch {
+                field,
+                expected,
+                found,
+            } => write!(f, "field `{field}` is a `{expected}`, not a `{found}`"),
+            SetFieldError::NotParsable { field, type_name } => write!(
+                f,
+                "field `{field}` is a `{type_name}`, which cannot be parsed from a string"
+            ),
+            SetFieldError::Parse {
+                field,
+                type_name,
+                source,
+            } => write!(f, "invalid `{type_name}` for field `{field}`: {source}"),
+        }
+    }
+}
+
+impl Error for SetFieldError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SetFieldError::Parse { source, .. } => Some(&**source),
+            _ => None,
+        }
+    }
+}
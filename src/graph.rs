@@ -0,0 +1,214 @@
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+use crate::{FieldInfo, FieldPath, PrimitiveKind, Segment, StructReflectionHelper};
+
+/// A reflected type, as a node of a [`TypeGraph`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeNode {
+    /// The type's name as written in its definition, e.g. `Node`.
+    pub name: &'static str,
+    /// The type's full name, as given by [`std::any::type_name`].
+    pub type_name: &'static str,
+}
+
+/// The reflected types reachable from a type, and which of them refer to which.
+///
+/// Built by [`struct_reflection_graph`](crate::StructReflection::struct_reflection_graph).
+/// Smart pointers, options and collections are looked through, so a
+/// `next: Option<Box<Node>>` or a `children: Vec<Node>` field is an edge from
+/// `Node` to itself, even when the collection is a single leaf of the names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeGraph {
+    nodes: Vec<TypeNode>,
+    edges: Vec<(usize, usize)>,
+    cycles: Vec<Vec<usize>>,
+}
+
+impl TypeGraph {
+    /// The types in the graph, starting with the one it was built for.
+    pub fn nodes(&self) -> &[TypeNode] {
+        &self.nodes
+    }
+
+    /// `(from, to)` pairs of indices into [`nodes`](TypeGraph::nodes), one per
+    /// type that has a field of another.
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// Every cycle found, as the indices of the types it goes through, starting
+    /// from the one closest to the root.
+    pub fn cycles(&self) -> &[Vec<usize>] {
+        &self.cycles
+    }
+
+    pub fn is_recursive(&self) -> bool {
+        !self.cycles.is_empty()
+    }
+
+    /// The node for `type_name`, added if it is not in the graph yet.
+    fn node(&mut self, type_name: &'static str, name: &'static str) -> usize {
+        match self
+            .nodes
+            .iter()
+            .position(|node| node.type_name == type_name)
+        {
+            Some(index) => index,
+            None => {
+                self.nodes.push(TypeNode { name, type_name });
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        if !self.edges.contains(&(from, to)) {
+            self.edges.push((from, to));
+        }
+    }
+
+    fn cycle(&mut self, cycle: Vec<usize>) {
+        // The same cycle can be reached through each of its types
+        let is_known = self.cycles.iter().any(|known| {
+            known.len() == cycle.len()
+                && (0..known.len()).any(|start| {
+                    known[start..]
+                        .iter()
+                        .chain(&known[..start])
+                        .eq(cycle.iter())
+                })
+        });
+        if !is_known {
+            self.cycles.push(cycle);
+        }
+    }
+}
+
+thread_local! {
+    /// `(type_name, name)` of the derived types being expanded, outermost first.
+    static STACK: RefCell<Vec<(&'static str, &'static str)>> = const { RefCell::new(Vec::new()) };
+    static GRAPH: RefCell<Option<TypeGraph>> = const { RefCell::new(None) };
+}
+
+/// Pops the expanded type off the stack, even if the expansion panics.
+struct StackGuard;
+
+impl Drop for StackGuard {
+    fn drop(&mut self) {
+        STACK.with(|stack| stack.borrow_mut().pop());
+    }
+}
+
+fn push(type_name: &'static str, name: &'static str) -> StackGuard {
+    STACK.with(|stack| stack.borrow_mut().push((type_name, name)));
+    StackGuard
+}
+
+fn with_graph(record: impl FnOnce(&mut TypeGraph)) {
+    GRAPH.with(|graph| {
+        if let Some(graph) = graph.borrow_mut().as_mut() {
+            record(graph);
+        }
+    });
+}
+
+/// Runs the type-level expansion of a derived type, unless the type is already
+/// being expanded further up: the fields of a recursive type are then a single
+/// `$ref:Name` entry.
+#[doc(hidden)]
+pub fn enter_type(
+    type_name: &'static str,
+    name: &'static str,
+    expand: impl FnOnce() -> Option<Vec<FieldInfo>>,
+) -> Option<Vec<FieldInfo>> {
+    let stack = STACK.with(|stack| stack.borrow().clone());
+
+    with_graph(|graph| {
+        let index = graph.node(type_name, name);
+        if let Some(&(parent_type_name, parent_name)) = stack.last() {
+            let parent = graph.node(parent_type_name, parent_name);
+            graph.edge(parent, index);
+        }
+    });
+
+    if let Some(start) = stack.iter().position(|&(entered, _)| entered == type_name) {
+        with_graph(|graph| {
+            let cycle = stack[start..]
+                .iter()
+                .map(|&(type_name, name)| graph.node(type_name, name))
+                .collect();
+            graph.cycle(cycle);
+        });
+
+        return Some(vec![FieldInfo {
+            path: FieldPath::from(Segment::Ref(name.to_string())),
+            type_name,
+            kind: PrimitiveKind::Other,
+            is_generic: false,
+        }]);
+    }
+
+    let _guard = push(type_name, name);
+    expand()
+}
+
+/// Runs the instance-level expansion of a derived type. Values are finite, so
+/// this never stops early, but options holding `None` fall back to the
+/// type-level expansion, which then sees the enclosing types.
+#[doc(hidden)]
+pub fn enter_instance(
+    type_name: &'static str,
+    name: &'static str,
+    expand: impl FnOnce() -> Option<Vec<FieldInfo>>,
+) -> Option<Vec<FieldInfo>> {
+    let _guard = push(type_name, name);
+    expand()
+}
+
+/// Builds the type graph of whatever `expand` expands.
+pub(crate) fn record(expand: impl FnOnce()) -> TypeGraph {
+    let outer = GRAPH.with(|graph| graph.borrow_mut().replace(TypeGraph::default()));
+    expand();
+    GRAPH
+        .with(|graph| std::mem::replace(&mut *graph.borrow_mut(), outer))
+        .unwrap_or_default()
+}
+
+/// A type held by a collection that is reflected as a single leaf, looked
+/// through for the type graph only.
+///
+/// Used as `(&ElementProbe::<T>::new()).probe_graph()`, which resolves to
+/// [`ProbeReflected`] when `T` is reflected and to [`ProbeOpaque`] otherwise.
+#[doc(hidden)]
+pub struct ElementProbe<T>(PhantomData<T>);
+
+impl<T> ElementProbe<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        ElementProbe(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait ProbeReflected {
+    fn probe_graph(&self);
+}
+
+impl<T: StructReflectionHelper> ProbeReflected for ElementProbe<T> {
+    fn probe_graph(&self) {
+        let is_recording = GRAPH.with(|graph| graph.borrow().is_some());
+        if is_recording {
+            T::field_infos();
+        }
+    }
+}
+
+#[doc(hidden)]
+pub trait ProbeOpaque {
+    fn probe_graph(&self);
+}
+
+impl<T> ProbeOpaque for &ElementProbe<T> {
+    fn probe_graph(&self) {}
+}
@@ -0,0 +1,45 @@
+use crate::FieldPath;
+
+/// Broad classification of a reflected leaf's type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrimitiveKind {
+    /// Signed and unsigned integers of any width.
+    Int,
+    /// `f32` and `f64`.
+    Float,
+    Bool,
+    Char,
+    /// `String` and `str`.
+    String,
+    /// Anything else reflected as a single entry.
+    Other,
+}
+
+impl PrimitiveKind {
+    /// Classifies a type from its [`std::any::type_name`].
+    ///
+    /// Used for leaves whose type is only known at runtime, such as generic
+    /// parameters.
+    pub fn from_type_name(type_name: &str) -> Self {
+        match type_name {
+            "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64"
+            | "i128" | "isize" => PrimitiveKind::Int,
+            "f32" | "f64" => PrimitiveKind::Float,
+            "bool" => PrimitiveKind::Bool,
+            "char" => PrimitiveKind::Char,
+            "str" | "alloc::string::String" => PrimitiveKind::String,
+            _ => PrimitiveKind::Other,
+        }
+    }
+}
+
+/// Description of one reflected leaf: its path and what its type is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldInfo {
+    pub path: FieldPath,
+    /// The leaf's type, as given by [`std::any::type_name`].
+    pub type_name: &'static str,
+    pub kind: PrimitiveKind,
+    /// Whether the leaf's type is one of the deriving type's generic parameters.
+    pub is_generic: bool,
+}
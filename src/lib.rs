@@ -1,41 +1,939 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ffi::{CStr, CString, OsStr, OsString};
 use std::marker::PhantomData;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize, Saturating, Wrapping,
+};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{
+    AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicU16, AtomicU32,
+    AtomicU64, AtomicU8, AtomicUsize,
+};
+use std::sync::{Arc, Mutex, RwLock, TryLockError};
+use std::time::{Duration, Instant, SystemTime};
 
+mod access;
+mod graph;
+mod info;
+mod names;
+mod path;
+mod row;
+mod value;
+mod visit;
+
+pub use access::{FieldMut, FieldRef, SetFieldError};
+#[doc(hidden)]
+pub use graph::{enter_instance, enter_type, ElementProbe, ProbeOpaque, ProbeReflected};
+pub use graph::{TypeGraph, TypeNode};
+pub use info::{FieldInfo, PrimitiveKind};
+#[doc(hidden)]
+pub use names::leak_names;
+pub use path::{FieldPath, ParseFieldPathError, Segment};
+pub use row::{FromRowError, MistypedColumn};
+#[doc(hidden)]
+pub use row::{OptionCheckpoint, RowReader, SkippedDefault, SkippedField, SkippedNoDefault};
 pub use struct_reflection_derive::StructReflection;
+pub use value::Value;
+#[doc(hidden)]
+pub use visit::FieldWalker;
+pub use visit::{FieldKey, FieldVisitor, Leaf, OptionStyle};
 
+/// Types whose field names can be obtained at runtime.
+///
+/// This is the trait implemented by `#[derive(StructReflection)]`, so it can be
+/// used as a bound in generic code (`fn dump<T: StructReflection>()`).
 pub trait StructReflection {
-    fn struct_reflection() -> Option<Vec<String>>;
+    /// Separator placed between the segments of the names returned by
+    /// [`struct_reflection`](StructReflection::struct_reflection).
+    ///
+    /// Set with `#[reflect(separator = "...")]`.
+    const SEPARATOR: &'static str = "__";
+
+    /// The number of names returned by [`struct_reflection`](StructReflection::struct_reflection),
+    /// as a constant, e.g. to size an array with one slot per field.
+    ///
+    /// Using it for a recursive type is a compile error, as the type has no
+    /// finite expansion at compile time.
+    const FIELD_COUNT: usize;
+
+    /// Whether any name has a named segment, so that flattening the type
+    /// inlines its names.
+    #[doc(hidden)]
+    const STRUCT_REFLECTION_HAS_NAMES: bool = true;
+
+    /// Whether the type is reflected as a single leaf, as a transparent struct
+    /// around a leaf is.
+    #[doc(hidden)]
+    const STRUCT_REFLECTION_IS_LEAF: bool = false;
+
+    /// Path and type information for every reflected field.
+    fn struct_reflection_fields() -> Option<Vec<FieldInfo>>;
+
+    /// Paths of all the reflected fields, with typed segments.
+    fn struct_reflection_paths() -> Option<Vec<FieldPath>> {
+        Self::struct_reflection_fields()
+            .map(|fields| fields.into_iter().map(|field| field.path).collect())
+    }
+
+    /// Names of all the reflected fields, each given as the list of its path
+    /// segments (`["address", "street"]` for `address__street`).
+    fn struct_reflection_segments() -> Option<Vec<Vec<String>>> {
+        Self::struct_reflection_paths().map(|paths| {
+            paths
+                .iter()
+                .map(|path| {
+                    path.segments()
+                        .iter()
+                        .map(Segment::to_flat_string)
+                        .collect()
+                })
+                .collect()
+        })
+    }
+
+    /// Names of all the reflected fields, with segments joined by [`SEPARATOR`](StructReflection::SEPARATOR).
+    fn struct_reflection() -> Option<Vec<String>> {
+        Self::struct_reflection_with_separator(Self::SEPARATOR)
+    }
+
+    /// Names of all the reflected fields, with segments joined by `separator`.
+    fn struct_reflection_with_separator(separator: &str) -> Option<Vec<String>> {
+        Self::struct_reflection_paths()
+            .map(|paths| paths.iter().map(|path| path.join(separator)).collect())
+    }
+
+    /// The names returned by [`struct_reflection`](StructReflection::struct_reflection),
+    /// computed once and kept for the rest of the program, so that repeated
+    /// calls do not allocate.
+    ///
+    /// Derived types without type or const parameters keep them in a `static`
+    /// of their own. Other types look them up by their [`TypeId`](std::any::TypeId),
+    /// as type names need not be unique.
+    fn struct_reflection_names() -> Option<&'static [&'static str]>
+    where
+        Self: 'static,
+    {
+        names::cached_names(std::any::TypeId::of::<Self>(), Self::struct_reflection)
+    }
+
+    /// The graph of the reflected types reachable from this one, exposing any
+    /// cycles between them.
+    fn struct_reflection_graph() -> TypeGraph {
+        graph::record(|| {
+            Self::struct_reflection_fields();
+        })
+    }
+
+    /// Path and type information for the fields of this particular value.
+    ///
+    /// The entries are those of [`struct_reflection_fields`](StructReflection::struct_reflection_fields),
+    /// except that `#[reflect(values)]` maps are expanded into their actual keys
+    /// and `#[reflect(elements)]` collections into their actual elements.
+    fn struct_reflection_instance_fields(&self) -> Option<Vec<FieldInfo>> {
+        Self::struct_reflection_fields()
+    }
+
+    /// Names of the fields of this particular value, with segments joined by
+    /// [`SEPARATOR`](StructReflection::SEPARATOR).
+    fn struct_reflection_instance(&self) -> Option<Vec<String>> {
+        self.struct_reflection_instance_fields().map(|fields| {
+            fields
+                .iter()
+                .map(|field| field.path.join(Self::SEPARATOR))
+                .collect()
+        })
+    }
+
+    /// The leaves of this particular value, borrowed, with their paths.
+    ///
+    /// The paths are those of [`struct_reflection_instance_fields`](StructReflection::struct_reflection_instance_fields),
+    /// less the ones that hold no value: the fields of options holding `None`
+    /// and of enum variants other than the current one. The contents of a
+    /// `Cell`, `RefCell`, `Mutex` or `RwLock` cannot be borrowed past it, so the
+    /// cell itself is the leaf.
+    fn struct_reflection_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        None
+    }
+
+    /// The leaf reflected under `name`, one of the names returned by
+    /// [`struct_reflection_instance`](StructReflection::struct_reflection_instance).
+    ///
+    /// ```
+    /// use struct_reflection::StructReflection;
+    ///
+    /// #[derive(StructReflection)]
+    /// struct Address {
+    ///     street: String,
+    /// }
+    ///
+    /// #[derive(StructReflection)]
+    /// struct User {
+    ///     address: Address,
+    /// }
+    ///
+    /// let user = User {
+    ///     address: Address {
+    ///         street: "Main St".to_string(),
+    ///     },
+    /// };
+    /// let street = user.struct_reflection_get("address__street").unwrap();
+    /// assert_eq!(street.downcast_ref::<String>().unwrap(), "Main St");
+    /// ```
+    fn struct_reflection_get(&self, name: &str) -> Option<&dyn Any>
+    where
+        Self: 'static,
+    {
+        self.struct_reflection_values()?
+            .into_iter()
+            .find(|field| field.path.join(Self::SEPARATOR) == name)
+            .map(|field| field.value)
+    }
+
+    /// The leaf reflected under `name`, if it is a `T`.
+    fn struct_reflection_get_as<T: Any>(&self, name: &str) -> Option<&T>
+    where
+        Self: 'static,
+    {
+        self.struct_reflection_get(name)?.downcast_ref()
+    }
+
+    /// The leaves of this particular value, mutably borrowed, with their paths.
+    ///
+    /// These are the leaves of [`struct_reflection_values`](StructReflection::struct_reflection_values),
+    /// less the `"union"` tag and the entries standing for a whole enum
+    /// variant, which cannot be assigned, and `Rc`, `Arc` and `Cow` values are
+    /// leaves, as their contents may be shared. An option of a leaf holding
+    /// `None` is listed under the leaf's path, and assigning it sets it to
+    /// `Some`.
+    fn struct_reflection_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        None
+    }
+
+    /// Mutable counterpart of [`struct_reflection_get`](StructReflection::struct_reflection_get).
+    fn struct_reflection_get_mut(&mut self, name: &str) -> Option<&mut dyn Any>
+    where
+        Self: 'static,
+    {
+        find_field_mut(self, name).ok().map(|field| field.value)
+    }
+
+    /// The leaf reflected under `name`, mutably borrowed, if it is a `T`.
+    fn struct_reflection_get_mut_as<T: Any>(&mut self, name: &str) -> Option<&mut T>
+    where
+        Self: 'static,
+    {
+        self.struct_reflection_get_mut(name)?.downcast_mut()
+    }
+
+    /// Replaces the leaf reflected under `name` with `value`, which must be of
+    /// the leaf's type.
+    fn struct_reflection_set<T: Any>(&mut self, name: &str, value: T) -> Result<(), SetFieldError>
+    where
+        Self: 'static,
+    {
+        find_field_mut(self, name)?
+            .set(value)
+            .map_err(|error| error.with_field(name))
+    }
+
+    /// Replaces the leaf reflected under `name` with the value parsed from `s`
+    /// by the `FromStr` impl of the leaf's type.
+    ///
+    /// ```
+    /// use struct_reflection::StructReflection;
+    ///
+    /// #[derive(StructReflection)]
+    /// struct Limits {
+    ///     cpu: u32,
+    /// }
+    ///
+    /// #[derive(StructReflection)]
+    /// struct Config {
+    ///     limits: Limits,
+    /// }
+    ///
+    /// let mut config = Config {
+    ///     limits: Limits { cpu: 1 },
+    /// };
+    /// config.struct_reflection_set_from_str("limits__cpu", "4").unwrap();
+    /// assert_eq!(config.limits.cpu, 4);
+    /// assert!(config.struct_reflection_set_from_str("limits__cpu", "many").is_err());
+    /// ```
+    ///
+    /// Integers, floats, `bool`, `char`, `String`, `PathBuf`, non-zero integers
+    /// and IP and socket addresses can be parsed; other leaves give
+    /// [`SetFieldError::NotParsable`].
+    fn struct_reflection_set_from_str(&mut self, name: &str, s: &str) -> Result<(), SetFieldError>
+    where
+        Self: 'static,
+    {
+        find_field_mut(self, name)?
+            .set_from_str(s)
+            .map_err(|error| error.with_field(name))
+    }
+
+    /// The values of this particular value, one per name of
+    /// [`struct_reflection`](StructReflection::struct_reflection) and in the
+    /// same order.
+    ///
+    /// ```
+    /// use struct_reflection::{StructReflection, Value};
+    ///
+    /// #[derive(StructReflection)]
+    /// struct Row {
+    ///     id: u32,
+    ///     label: Option<String>,
+    /// }
+    ///
+    /// let row = Row { id: 7, label: None };
+    /// assert_eq!(
+    ///     Row::struct_reflection().unwrap(),
+    ///     ["id", "label__optional"]
+    /// );
+    /// assert_eq!(row.to_row(), [Value::UInt(7), Value::Null]);
+    /// ```
+    ///
+    /// Columns without a value are [`Value::Null`]: the fields of options
+    /// holding `None`, of enum variants other than the current one, and the
+    /// `{}` and `[]` columns of `#[reflect(values)]` maps and
+    /// `#[reflect(elements)]` collections, whose contents vary from one value
+    /// to the next. Leaves that are not numbers, `bool`, `char` or strings are
+    /// [`Value::Opaque`].
+    fn to_row(&self) -> Vec<Value>
+    where
+        Self: 'static,
+    {
+        let Some(fields) = Self::struct_reflection_fields() else {
+            return Vec::new();
+        };
+        let mut values: HashMap<FieldPath, &dyn Any> = self
+            .struct_reflection_values()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|field| (field.path, field.value))
+            .collect();
+
+        fields
+            .into_iter()
+            .map(|field| match values.remove(&field.path) {
+                Some(value) => Value::from_any(value, field.type_name),
+                None => Value::Null,
+            })
+            .collect()
+    }
+
+    /// Rebuilds a value from its columns, keyed by the names of
+    /// [`struct_reflection`](StructReflection::struct_reflection).
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// use struct_reflection::{StructReflection, Value};
+    ///
+    /// #[derive(Debug, StructReflection)]
+    /// struct Row {
+    ///     id: u32,
+    ///     label: Option<String>,
+    /// }
+    ///
+    /// let columns = HashMap::from([
+    ///     ("id".to_string(), Value::String("7".to_string())),
+    ///     ("label__optional".to_string(), Value::Null),
+    /// ]);
+    /// let row = Row::from_map(&columns).unwrap();
+    /// assert_eq!((row.id, row.label), (7, None));
+    ///
+    /// let error = Row::from_map(&HashMap::new()).unwrap_err();
+    /// assert_eq!(error.missing, ["id"]);
+    /// ```
+    ///
+    /// Leaves are converted from values of a matching kind, or parsed from
+    /// strings. The columns of an option are optional: the option is `None`
+    /// when none of them hold a value. A `"union"` enum takes the variant named
+    /// by its tag, and other enums the first variant with a value in any of its
+    /// columns. Skipped fields take their `Default` value, and are reported in
+    /// [`FromRowError::no_default`] if their type has none.
+    ///
+    /// Leaves that are not numbers, `bool`, `char`, strings, paths or network
+    /// addresses cannot be rebuilt, and their columns are reported as mistyped.
+    /// `#[reflect(values)]` maps and `#[reflect(elements)]` collections are
+    /// read back empty from the null columns standing for their contents.
+    fn from_map(columns: &HashMap<String, Value>) -> Result<Self, FromRowError>
+    where
+        Self: Sized + 'static,
+    {
+        let names = Self::struct_reflection().unwrap_or_default();
+        let mut extra: Vec<String> = columns
+            .keys()
+            .filter(|column| !names.contains(column))
+            .cloned()
+            .collect();
+        extra.sort();
+
+        let mut reader = RowReader::new(columns, Self::SEPARATOR);
+        let value = Self::struct_reflection_read(&mut reader, FieldPath::new());
+        reader.finish(value, extra)
+    }
+
+    /// Rebuilds a value from a row ordered like the names of
+    /// [`struct_reflection`](StructReflection::struct_reflection), as returned
+    /// by [`to_row`](StructReflection::to_row).
+    ///
+    /// Follows the rules of [`from_map`](StructReflection::from_map); values
+    /// past the last name are reported as extra by their index.
+    fn from_row(row: &[Value]) -> Result<Self, FromRowError>
+    where
+        Self: Sized + 'static,
+    {
+        let names = Self::struct_reflection().unwrap_or_default();
+        let columns: HashMap<String, Value> =
+            names.iter().cloned().zip(row.iter().cloned()).collect();
+
+        let mut reader = RowReader::new(&columns, Self::SEPARATOR);
+        let value = Self::struct_reflection_read(&mut reader, FieldPath::new());
+        reader.finish(
+            value,
+            (names.len()..row.len()).map(|i| i.to_string()).collect(),
+        )
+    }
+
+    /// Reads the value at `prefix` for [`from_map`](StructReflection::from_map),
+    /// implemented by the derive macro.
+    #[doc(hidden)]
+    fn struct_reflection_read(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: Sized + 'static,
+    {
+        reader.unsupported(prefix, std::any::type_name::<Self>())
+    }
+
+    /// Walks the reflected fields of the type, calling `visitor` for every
+    /// struct, array, tuple, option and map on the way and for every leaf.
+    ///
+    /// This is the walk behind [`struct_reflection_fields`](StructReflection::struct_reflection_fields),
+    /// without building paths: see [`FieldVisitor`] for the order of the
+    /// calls.
+    fn struct_reflection_visit(visitor: &mut dyn FieldVisitor) {
+        Self::struct_reflection_walk(&mut FieldWalker::new(visitor), FieldKey::Named(""));
+    }
+
+    /// Walks the fields of this particular value, like
+    /// [`struct_reflection_visit`](StructReflection::struct_reflection_visit)
+    /// but with the leaves of [`struct_reflection_values`](StructReflection::struct_reflection_values):
+    /// options holding `None` and enum variants other than the current one
+    /// have no contents, and collections and maps are walked into their
+    /// actual elements and keys.
+    ///
+    /// ```
+    /// use struct_reflection::{FieldKey, FieldVisitor, Leaf, StructReflection};
+    ///
+    /// #[derive(StructReflection)]
+    /// struct Reading {
+    ///     sensor: String,
+    ///     samples: [f64; 3],
+    /// }
+    ///
+    /// // Adds up the floats
+    /// struct Total(f64);
+    ///
+    /// impl FieldVisitor for Total {
+    ///     fn leaf(&mut self, _key: FieldKey<'_>, leaf: Leaf<'_>) {
+    ///         if let Some(value) = leaf.value.and_then(|value| value.downcast_ref::<f64>()) {
+    ///             self.0 += value;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let reading = Reading {
+    ///     sensor: "north".to_string(),
+    ///     samples: [0.5, 1.5, 2.0],
+    /// };
+    /// let mut total = Total(0.0);
+    /// reading.struct_reflection_visit_values(&mut total);
+    /// assert_eq!(total.0, 4.0);
+    /// ```
+    fn struct_reflection_visit_values(&self, visitor: &mut dyn FieldVisitor)
+    where
+        Self: 'static,
+    {
+        self.struct_reflection_walk_value(&mut FieldWalker::new(visitor), FieldKey::Named(""));
+    }
+
+    /// Visits the type as the node at `key` for [`struct_reflection_visit`](StructReflection::struct_reflection_visit),
+    /// implemented by the derive macro.
+    #[doc(hidden)]
+    fn struct_reflection_walk(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        walker.leaf(key, opaque_leaf::<Self>(None));
+    }
+
+    /// Visits the value as the node at `key` for [`struct_reflection_visit_values`](StructReflection::struct_reflection_visit_values),
+    /// implemented by the derive macro.
+    #[doc(hidden)]
+    fn struct_reflection_walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        let _ = (walker, key);
+    }
+}
+
+fn find_field_mut<'a, T: StructReflection + ?Sized + 'static>(
+    value: &'a mut T,
+    name: &str,
+) -> Result<FieldMut<'a>, SetFieldError> {
+    value
+        .struct_reflection_values_mut()
+        .into_iter()
+        .flatten()
+        .find(|field| field.path.join(T::SEPARATOR) == name)
+        .ok_or_else(|| SetFieldError::UnknownField(name.to_string()))
 }
 
-pub trait StructReflectionHelper {
-    fn struct_reflection() -> Option<Vec<String>>;
+/// [`StructReflectionHelper::HAS_NAMES`] of a field's type, for derived impls.
+#[doc(hidden)]
+pub const fn has_names<T: StructReflectionHelper + ?Sized>() -> bool {
+    T::HAS_NAMES
 }
 
+/// [`StructReflectionHelper::IS_LEAF`] of a field's type, for derived impls.
+#[doc(hidden)]
+pub const fn is_leaf<T: StructReflectionHelper + ?Sized>() -> bool {
+    T::IS_LEAF
+}
+
+/// Number of entries of a field's type, for derived impls. Going through a function
+/// call keeps the constant from being evaluated until it is used, so that
+/// recursive types, which have none, still compile.
+#[doc(hidden)]
+pub const fn field_count<T: StructReflectionHelper + ?Sized>() -> usize {
+    T::ENTRY_COUNT
+}
+
+/// Panics if two fields of `type_name` are reflected under the same path, which
+/// the derive macro cannot rule out for `#[reflect(flatten)]` fields.
+#[doc(hidden)]
+pub fn check_unique_paths(type_name: &str, fields: &[FieldInfo]) {
+    for (i, field) in fields.iter().enumerate() {
+        if fields[..i].iter().any(|other| other.path == field.path) {
+            panic!(
+                "`{type_name}` reflects `{}` more than once; a flattened field collides with another field",
+                field.path
+            );
+        }
+    }
+}
+
+/// A single leaf borrowing `value`, for `field_values` impls.
+fn leaf_value<T: Any>(value: &T) -> Option<Vec<FieldRef<'_>>> {
+    Some(vec![FieldRef {
+        path: FieldPath::new(),
+        value,
+    }])
+}
+
+fn leaf_value_mut<T: Any>(value: &mut T) -> Option<Vec<FieldMut<'_>>> {
+    Some(vec![FieldMut::new(FieldPath::new(), value)])
+}
+
+/// A leaf of type `T` that nothing is known about, holding `value` in walks of
+/// a value.
+fn opaque_leaf<T: ?Sized>(value: Option<&dyn Any>) -> Leaf<'_> {
+    Leaf {
+        type_name: std::any::type_name::<T>(),
+        kind: PrimitiveKind::Other,
+        is_generic: false,
+        value,
+    }
+}
+
+/// Visits `value` as a single leaf, for `walk_value` impls of types that
+/// cannot be walked into.
+fn walk_leaf<T: Any>(walker: &mut FieldWalker<'_>, key: FieldKey<'_>, value: &T) {
+    walker.leaf(key, opaque_leaf::<T>(Some(value)));
+}
+
+/// Visits a pointer to `T`, which is the leaf when `T` is an unsized one.
+fn walk_pointer<T: StructReflectionHelper + ?Sized + 'static, P: Any>(
+    walker: &mut FieldWalker<'_>,
+    key: FieldKey<'_>,
+    pointer: &P,
+    pointee: &T,
+) {
+    if T::UNSIZED_LEAF {
+        let type_name = std::any::type_name::<T>();
+        walker.leaf(
+            key,
+            Leaf {
+                type_name: std::any::type_name::<P>(),
+                kind: PrimitiveKind::from_type_name(type_name),
+                is_generic: false,
+                value: Some(pointer),
+            },
+        );
+    } else {
+        pointee.walk_value(walker, key);
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Implementation detail used by the generated code to reflect field types.
+///
+/// Every `StructReflection` type implements it through the blanket impl below,
+/// alongside the built-in impls for arrays, collections, maps, smart pointers,
+/// `Option<T>` and `PhantomData<T>`.
+/// It is sealed and not meant to be implemented or called directly.
+#[doc(hidden)]
+pub trait StructReflectionHelper: sealed::Sealed {
+    /// Number of entries, counting a leaf as one.
+    const ENTRY_COUNT: usize;
+
+    /// Whether any entry has a named segment. A flattened field is only
+    /// inlined into its parent when it has names to inline, and keeps its own
+    /// segment otherwise.
+    const HAS_NAMES: bool = false;
+
+    /// Whether the type is a single leaf, with no entries nested below it. A
+    /// `prefix` field only takes its prefix when it has nested entries.
+    const IS_LEAF: bool = true;
+
+    /// Whether the type is a leaf that cannot be borrowed as `dyn Any`, so that
+    /// pointers to it borrow themselves instead.
+    const UNSIZED_LEAF: bool = false;
+
+    fn field_infos() -> Option<Vec<FieldInfo>>;
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        Self::field_infos()
+    }
+
+    /// `None` if nothing can be borrowed, as for unsized leaves.
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        None
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        None
+    }
+
+    /// The entries of an option of the type holding `None`, which assigning
+    /// sets to `Some`; `None` if it has none, as only leaves can be built from
+    /// a single value.
+    fn unset_values_mut(option: &mut Option<Self>) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: Sized + 'static,
+    {
+        let _ = option;
+        None
+    }
+
+    /// The single leaf of a collapsed option of the type: the value it holds,
+    /// or the option itself while it holds `None`, which assigning sets to
+    /// `Some`.
+    fn collapsed_value_mut(option: &mut Option<Self>) -> FieldMut<'_>
+    where
+        Self: Sized + 'static,
+    {
+        match option {
+            Some(value) => FieldMut::new(FieldPath::new(), value),
+            option => FieldMut::unset(FieldPath::new(), option),
+        }
+    }
+
+    /// Reads a value from the columns of a row; the default is for leaves
+    /// that cannot be rebuilt.
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: Sized + 'static,
+    {
+        reader.unsupported(prefix, std::any::type_name::<Self>())
+    }
+
+    /// [`read_row`](StructReflectionHelper::read_row) for pointers, which
+    /// unsized types can also be read into.
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        reader.unsupported(prefix, std::any::type_name::<Self>())
+    }
+
+    /// [`field_values`](StructReflectionHelper::field_values) of a box holding
+    /// the type, which is itself the leaf when its contents cannot be borrowed.
+    #[allow(clippy::borrowed_box)]
+    fn boxed_field_values(this: &Box<Self>) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        (**this).field_values().or_else(|| leaf_value(this))
+    }
+
+    /// [`field_values_mut`](StructReflectionHelper::field_values_mut) of a box
+    /// holding the type. Asked upfront, as the box cannot be borrowed again
+    /// once its contents are.
+    fn boxed_field_values_mut(this: &mut Box<Self>) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        if Self::UNSIZED_LEAF {
+            leaf_value_mut(this)
+        } else {
+            (**this).field_values_mut()
+        }
+    }
+
+    /// [`walk_value`](StructReflectionHelper::walk_value) of a box holding the
+    /// type.
+    #[allow(clippy::borrowed_box)]
+    fn walk_boxed_value(this: &Box<Self>, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_pointer(walker, key, this, &**this);
+    }
+
+    /// Visits the type as the node at `key`; the default is for types without
+    /// fields, which are a single leaf.
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        walker.leaf(key, opaque_leaf::<Self>(None));
+    }
+
+    /// Visits the value as the node at `key`; does nothing if it cannot be
+    /// borrowed, as for unsized leaves.
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        let _ = (walker, key);
+    }
+}
+
+impl<T: StructReflection> sealed::Sealed for T {}
+
 /// Implementation of StructReflectionHelper for any type that implements StructReflection.
-/// This bridges the two traits, allowing types with #[derive(StructReflection)] to work
-/// in contexts that require StructReflectionHelper.
+/// This bridges the two traits, allowing types with #[derive(StructReflection)] to be
+/// used as field types of other derived structs.
 impl<T: StructReflection> StructReflectionHelper for T {
-    fn struct_reflection() -> Option<Vec<String>> {
-        T::struct_reflection()
+    const ENTRY_COUNT: usize = <T as StructReflection>::FIELD_COUNT;
+    const HAS_NAMES: bool = T::STRUCT_REFLECTION_HAS_NAMES;
+    const IS_LEAF: bool = T::STRUCT_REFLECTION_IS_LEAF;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        T::struct_reflection_fields()
+    }
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        self.struct_reflection_instance_fields()
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        self.struct_reflection_values()
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        self.struct_reflection_values_mut()
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        T::struct_reflection_read(reader, prefix)
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        T::struct_reflection_read(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        T::struct_reflection_walk(walker, key);
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        self.struct_reflection_walk_value(walker, key);
     }
 }
 
+impl<T: StructReflectionHelper, const N: usize> sealed::Sealed for [T; N] {}
+
 impl<T: StructReflectionHelper, const N: usize> StructReflectionHelper for [T; N] {
-    fn struct_reflection() -> Option<Vec<String>> {
-        match T::struct_reflection() {
+    const ENTRY_COUNT: usize = N * T::ENTRY_COUNT;
+    const HAS_NAMES: bool = T::HAS_NAMES;
+    const IS_LEAF: bool = false;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        match T::field_infos() {
             Some(inner_fields) => {
                 let mut fields = Vec::new();
 
                 for i in 0..N {
                     for field in &inner_fields {
-                        fields.push(format!("{i}__{field}"));
+                        let mut path = FieldPath::from(Segment::ArrayIndex(i));
+                        path.extend(field.path.segments().iter().cloned());
+                        fields.push(FieldInfo {
+                            path,
+                            ..field.clone()
+                        });
                     }
                 }
 
                 Some(fields)
             }
-            None => Some((0..N).map(|i| i.to_string()).collect()),
+            None => {
+                let type_name = std::any::type_name::<T>();
+                Some(
+                    (0..N)
+                        .map(|i| FieldInfo {
+                            path: Segment::ArrayIndex(i).into(),
+                            type_name,
+                            kind: PrimitiveKind::Other,
+                            is_generic: false,
+                        })
+                        .collect(),
+                )
+            }
         }
     }
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        let mut fields = Vec::new();
+
+        for (i, element) in self.iter().enumerate() {
+            match element.instance_field_infos() {
+                Some(inner_fields) => {
+                    for field in inner_fields {
+                        let mut path = FieldPath::from(Segment::ArrayIndex(i));
+                        path.extend(field.path.segments().iter().cloned());
+                        fields.push(FieldInfo { path, ..field });
+                    }
+                }
+                None => {
+                    let type_name = std::any::type_name::<T>();
+                    fields.push(FieldInfo {
+                        path: Segment::ArrayIndex(i).into(),
+                        type_name,
+                        kind: PrimitiveKind::Other,
+                        is_generic: false,
+                    });
+                }
+            }
+        }
+
+        Some(fields)
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        let mut fields = Vec::new();
+
+        for (i, element) in self.iter().enumerate() {
+            match element.field_values() {
+                Some(inner_fields) => {
+                    for field in inner_fields {
+                        let mut path = FieldPath::from(Segment::ArrayIndex(i));
+                        path.extend(field.path);
+                        fields.push(FieldRef { path, ..field });
+                    }
+                }
+                None => fields.push(FieldRef {
+                    path: Segment::ArrayIndex(i).into(),
+                    value: element,
+                }),
+            }
+        }
+
+        Some(fields)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        let mut fields = Vec::new();
+
+        for (i, element) in self.iter_mut().enumerate() {
+            for mut field in element.field_values_mut().into_iter().flatten() {
+                let mut path = FieldPath::from(Segment::ArrayIndex(i));
+                path.extend(field.path);
+                field.path = path;
+                fields.push(field);
+            }
+        }
+
+        Some(fields)
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        // Every element is read, so that all of their columns are checked
+        let elements: Vec<Option<T>> = (0..N)
+            .map(|i| T::read_row(reader, prefix.child(Segment::ArrayIndex(i))))
+            .collect();
+        let elements: Vec<T> = elements.into_iter().collect::<Option<_>>()?;
+        elements.try_into().ok()
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        walker.visit_array(key, Some(N), |walker| {
+            for i in 0..N {
+                T::walk_type(walker, FieldKey::ArrayIndex(i));
+            }
+        });
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walker.visit_array(key, Some(N), |walker| {
+            for (i, element) in self.iter().enumerate() {
+                element.walk_value(walker, FieldKey::ArrayIndex(i));
+            }
+        });
+    }
 }
 
 // Note on Option<T> implementation:
@@ -44,20 +942,970 @@ impl<T: StructReflectionHelper, const N: usize> StructReflectionHelper for [T; N
 // - For primitives: return a single "optional" field
 // - For structs: return internal fields with "__optional" suffix
 //
-// This isn't currently possible in stable Rust due to trait coherence rules
-// and lack of specialization. As a compromise, we use a simplified implementation
-// that treats all Option<T> the same way, regardless of what T is.
-//
-// This might be improved in future versions when Rust's type system evolves.
+// This isn't possible in a trait impl on stable Rust due to trait coherence rules
+// and lack of specialization. The derive macro recognises `Option<...>` fields
+// syntactically and expands the inner type itself, so this impl is only reached
+// for options it cannot see through, such as those hidden behind a type alias.
+impl<T> sealed::Sealed for Option<T> {}
+
 impl<T> StructReflectionHelper for Option<T> {
-    fn struct_reflection() -> Option<Vec<String>> {
-        // Simple implementation that always returns "optional"
-        Some(vec!["optional".to_string()])
+    const ENTRY_COUNT: usize = 1;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        // The whole option is one collapsed leaf, holding the option's value
+        let type_name = std::any::type_name::<T>();
+        Some(vec![FieldInfo {
+            path: Segment::OptionalMarker.into(),
+            type_name,
+            kind: PrimitiveKind::from_type_name(type_name),
+            is_generic: false,
+        }])
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        let fields = self.as_ref().map(|value| FieldRef {
+            path: Segment::OptionalMarker.into(),
+            value,
+        });
+        Some(fields.into_iter().collect())
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        let field = match self {
+            Some(value) => FieldMut::new(Segment::OptionalMarker.into(), value),
+            option => FieldMut::unset(Segment::OptionalMarker.into(), option),
+        };
+        Some(vec![field])
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        reader.optional_leaf(prefix.child(Segment::OptionalMarker))
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        walker.visit_option(key, OptionStyle::Collapse, |walker| {
+            walker.leaf(FieldKey::Optional, opaque_leaf::<T>(None));
+        });
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walker.visit_option(key, OptionStyle::Collapse, |walker| {
+            if let Some(value) = self {
+                walk_leaf(walker, FieldKey::Optional, value);
+            }
+        });
     }
 }
 
+impl<T> sealed::Sealed for PhantomData<T> {}
+
 impl<T> StructReflectionHelper for PhantomData<T> {
-    fn struct_reflection() -> Option<Vec<String>> {
+    const ENTRY_COUNT: usize = 1;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
         None
     }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value(self)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn read_row(_reader: &mut RowReader<'_>, _prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        Some(PhantomData)
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_leaf(walker, key, self);
+    }
+}
+
+// Types that are reflected as a single entry of the given kind. The entry has
+// an empty path, which the containing field's path is prepended to. Values are
+// assigned from strings with their `FromStr` impl, except for `unparsable`
+// types, which have none. Unsized types cannot be borrowed as `dyn Any`, so
+// whatever holds them is the value. Rows are read into sized leaves by
+// `Value::to_leaf`, and into pointers to unsized ones through their owned form.
+macro_rules! impl_leaf {
+    (@impl $kind:ident $ty:ty { $($items:tt)* }) => {
+        impl sealed::Sealed for $ty {}
+
+        impl StructReflectionHelper for $ty {
+            const ENTRY_COUNT: usize = 1;
+
+            fn field_infos() -> Option<Vec<FieldInfo>> {
+                Some(vec![FieldInfo {
+                    path: FieldPath::new(),
+                    type_name: std::any::type_name::<$ty>(),
+                    kind: PrimitiveKind::$kind,
+                    is_generic: false,
+                }])
+            }
+
+            fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+                walker.leaf(key, Leaf {
+                    type_name: std::any::type_name::<$ty>(),
+                    kind: PrimitiveKind::$kind,
+                    is_generic: false,
+                    value: None,
+                });
+            }
+
+            $($items)*
+        }
+    };
+    (@sized $kind:ident $ty:ty { $($items:tt)* }) => {
+        impl_leaf!(@impl $kind $ty {
+            fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self> {
+                reader.leaf(prefix)
+            }
+
+            fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>> {
+                reader.leaf(prefix).map(Box::new)
+            }
+
+            fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+                walker.leaf(key, Leaf {
+                    type_name: std::any::type_name::<$ty>(),
+                    kind: PrimitiveKind::$kind,
+                    is_generic: false,
+                    value: Some(self),
+                });
+            }
+
+            $($items)*
+        });
+    };
+    // Unsized leaves are read into pointers through their owned form, if given
+    (unsized $kind:ident => $($ty:ty $(as $owned:ty)?),+ $(,)?) => {
+        $(
+            impl_leaf!(@impl $kind $ty {
+                const UNSIZED_LEAF: bool = true;
+
+                $(
+                    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>> {
+                        reader.leaf::<$owned>(prefix).map(Box::from)
+                    }
+                )?
+            });
+        )+
+    };
+    (unparsable $kind:ident => $($ty:ty),+ $(,)?) => {
+        $(
+            impl_leaf!(@sized $kind $ty {
+                fn field_values(&self) -> Option<Vec<FieldRef<'_>>> {
+                    leaf_value(self)
+                }
+
+                fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>> {
+                    leaf_value_mut(self)
+                }
+
+                fn unset_values_mut(option: &mut Option<Self>) -> Option<Vec<FieldMut<'_>>> {
+                    Some(vec![FieldMut::unset(FieldPath::new(), option)])
+                }
+            });
+        )+
+    };
+    ($kind:ident => $($ty:ty),+ $(,)?) => {
+        $(
+            impl_leaf!(@sized $kind $ty {
+                fn field_values(&self) -> Option<Vec<FieldRef<'_>>> {
+                    leaf_value(self)
+                }
+
+                fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>> {
+                    Some(vec![FieldMut::parsable(FieldPath::new(), self)])
+                }
+
+                fn unset_values_mut(option: &mut Option<Self>) -> Option<Vec<FieldMut<'_>>> {
+                    Some(vec![FieldMut::parsable_unset(FieldPath::new(), option)])
+                }
+
+                fn collapsed_value_mut(option: &mut Option<Self>) -> FieldMut<'_> {
+                    match option {
+                        Some(value) => FieldMut::parsable(FieldPath::new(), value),
+                        option => FieldMut::parsable_unset(FieldPath::new(), option),
+                    }
+                }
+            });
+        )+
+    };
+}
+
+impl_leaf!(Int => u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+impl_leaf!(Int => NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128, NonZeroUsize);
+impl_leaf!(Int => NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128, NonZeroIsize);
+impl_leaf!(unparsable Int => AtomicU8, AtomicU16, AtomicU32, AtomicU64, AtomicUsize);
+impl_leaf!(unparsable Int => AtomicI8, AtomicI16, AtomicI32, AtomicI64, AtomicIsize);
+impl_leaf!(Float => f32, f64);
+impl_leaf!(Bool => bool);
+impl_leaf!(unparsable Bool => AtomicBool);
+impl_leaf!(Char => char);
+impl_leaf!(String => String);
+impl_leaf!(unsized String => str as String);
+impl_leaf!(unparsable Other => Duration, Instant, SystemTime);
+impl_leaf!(Other => PathBuf);
+impl_leaf!(unparsable Other => OsString, CString);
+impl_leaf!(unsized Other => Path as PathBuf, OsStr, CStr);
+impl_leaf!(Other => IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6);
+
+// Wrapping arithmetic does not change what a number is
+impl<T: StructReflectionHelper> sealed::Sealed for Wrapping<T> {}
+
+impl<T: StructReflectionHelper> StructReflectionHelper for Wrapping<T> {
+    const ENTRY_COUNT: usize = T::ENTRY_COUNT;
+    const HAS_NAMES: bool = T::HAS_NAMES;
+    const IS_LEAF: bool = T::IS_LEAF;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        T::field_infos()
+    }
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        self.0.instance_field_infos()
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        self.0.field_values()
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        self.0.field_values_mut()
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        T::read_row(reader, prefix).map(Wrapping)
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        T::walk_type(walker, key);
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        self.0.walk_value(walker, key);
+    }
+}
+
+impl<T: StructReflectionHelper> sealed::Sealed for Saturating<T> {}
+
+impl<T: StructReflectionHelper> StructReflectionHelper for Saturating<T> {
+    const ENTRY_COUNT: usize = T::ENTRY_COUNT;
+    const HAS_NAMES: bool = T::HAS_NAMES;
+    const IS_LEAF: bool = T::IS_LEAF;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        T::field_infos()
+    }
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        self.0.instance_field_infos()
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        self.0.field_values()
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        self.0.field_values_mut()
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        T::read_row(reader, prefix).map(Saturating)
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        T::walk_type(walker, key);
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        self.0.walk_value(walker, key);
+    }
+}
+
+// Collections have no fixed length, so they are reflected as a single leaf. The
+// derive macro expands their element type itself for `#[reflect(elements)]`
+// fields. `Box<[T]>` goes through the `Box<T>` impl below.
+impl<T> sealed::Sealed for Vec<T> {}
+
+impl<T> StructReflectionHelper for Vec<T> {
+    const ENTRY_COUNT: usize = 1;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        None
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value(self)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_leaf(walker, key, self);
+    }
+}
+
+impl<T> sealed::Sealed for VecDeque<T> {}
+
+impl<T> StructReflectionHelper for VecDeque<T> {
+    const ENTRY_COUNT: usize = 1;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        None
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value(self)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_leaf(walker, key, self);
+    }
+}
+
+impl<T> sealed::Sealed for [T] {}
+
+impl<T> StructReflectionHelper for [T] {
+    const ENTRY_COUNT: usize = 1;
+    const UNSIZED_LEAF: bool = true;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        None
+    }
+}
+
+// Maps are single leaves as well; `#[reflect(values)]` fields are expanded by
+// the derive macro.
+impl<K, V, S> sealed::Sealed for HashMap<K, V, S> {}
+
+impl<K, V, S> StructReflectionHelper for HashMap<K, V, S> {
+    const ENTRY_COUNT: usize = 1;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        None
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value(self)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_leaf(walker, key, self);
+    }
+}
+
+impl<K, V> sealed::Sealed for BTreeMap<K, V> {}
+
+impl<K, V> StructReflectionHelper for BTreeMap<K, V> {
+    const ENTRY_COUNT: usize = 1;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        None
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value(self)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_leaf(walker, key, self);
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K, V, S> sealed::Sealed for indexmap::IndexMap<K, V, S> {}
+
+#[cfg(feature = "indexmap")]
+impl<K, V, S> StructReflectionHelper for indexmap::IndexMap<K, V, S> {
+    const ENTRY_COUNT: usize = 1;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        None
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value(self)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_leaf(walker, key, self);
+    }
+}
+
+// Smart pointers and cells are transparent: they are reflected as the type they
+// wrap. `Box` is a fundamental type, so a blanket-style `StructReflectionHelper`
+// impl for it would overlap with the one for `StructReflection` types; it
+// implements `StructReflection` around `StructReflection` types instead, and
+// the derive macro reaches other boxed types through the `boxed_*` methods of
+// the type they hold.
+impl<T: StructReflection> StructReflection for Box<T> {
+    const FIELD_COUNT: usize = T::FIELD_COUNT;
+    const STRUCT_REFLECTION_HAS_NAMES: bool = T::STRUCT_REFLECTION_HAS_NAMES;
+    const STRUCT_REFLECTION_IS_LEAF: bool = T::STRUCT_REFLECTION_IS_LEAF;
+
+    fn struct_reflection_fields() -> Option<Vec<FieldInfo>> {
+        T::struct_reflection_fields()
+    }
+
+    fn struct_reflection_instance_fields(&self) -> Option<Vec<FieldInfo>> {
+        (**self).struct_reflection_instance_fields()
+    }
+
+    fn struct_reflection_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        T::boxed_field_values(self)
+    }
+
+    fn struct_reflection_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        T::boxed_field_values_mut(self)
+    }
+
+    fn struct_reflection_read(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        T::read_boxed(reader, prefix)
+    }
+
+    fn struct_reflection_walk(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        T::walk_type(walker, key);
+    }
+
+    fn struct_reflection_walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        T::walk_boxed_value(self, walker, key);
+    }
+}
+
+impl<T: StructReflectionHelper + ?Sized> sealed::Sealed for Rc<T> {}
+
+impl<T: StructReflectionHelper + ?Sized> StructReflectionHelper for Rc<T> {
+    const ENTRY_COUNT: usize = T::ENTRY_COUNT;
+    const HAS_NAMES: bool = T::HAS_NAMES;
+    const IS_LEAF: bool = T::IS_LEAF;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        T::field_infos()
+    }
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        (**self).instance_field_infos()
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        (**self).field_values().or_else(|| leaf_value(self))
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        T::read_boxed(reader, prefix).map(Rc::from)
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        T::walk_type(walker, key);
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_pointer(walker, key, self, &**self);
+    }
+}
+
+impl<T: StructReflectionHelper + ?Sized> sealed::Sealed for Arc<T> {}
+
+impl<T: StructReflectionHelper + ?Sized> StructReflectionHelper for Arc<T> {
+    const ENTRY_COUNT: usize = T::ENTRY_COUNT;
+    const HAS_NAMES: bool = T::HAS_NAMES;
+    const IS_LEAF: bool = T::IS_LEAF;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        T::field_infos()
+    }
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        (**self).instance_field_infos()
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        (**self).field_values().or_else(|| leaf_value(self))
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        T::read_boxed(reader, prefix).map(Arc::from)
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        T::walk_type(walker, key);
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_pointer(walker, key, self, &**self);
+    }
+}
+
+impl<B: StructReflectionHelper + ToOwned + ?Sized> sealed::Sealed for Cow<'_, B> {}
+
+impl<B: StructReflectionHelper + ToOwned + ?Sized> StructReflectionHelper for Cow<'_, B> {
+    const ENTRY_COUNT: usize = B::ENTRY_COUNT;
+    const HAS_NAMES: bool = B::HAS_NAMES;
+    const IS_LEAF: bool = B::IS_LEAF;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        B::field_infos()
+    }
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        (**self).instance_field_infos()
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        (**self).field_values().or_else(|| leaf_value(self))
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        B::walk_type(walker, key);
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_pointer(walker, key, self, &**self);
+    }
+}
+
+// The contents of a `Cell` cannot be borrowed, so its instance fields are those
+// of the wrapped type. Cells and locks are sized so that value walks can borrow
+// the whole cell instead, as whatever they lend out does not outlive the guard.
+impl<T: StructReflectionHelper> sealed::Sealed for Cell<T> {}
+
+impl<T: StructReflectionHelper> StructReflectionHelper for Cell<T> {
+    const ENTRY_COUNT: usize = T::ENTRY_COUNT;
+    const HAS_NAMES: bool = T::HAS_NAMES;
+    const IS_LEAF: bool = T::IS_LEAF;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        T::field_infos()
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value(self)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        T::read_row(reader, prefix).map(Cell::new)
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        T::walk_type(walker, key);
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_leaf(walker, key, self);
+    }
+}
+
+// `RefCell`, `Mutex` and `RwLock` are walked into when their contents can be
+// accessed without blocking or panicking, and reflected by type otherwise.
+impl<T: StructReflectionHelper> sealed::Sealed for RefCell<T> {}
+
+impl<T: StructReflectionHelper> StructReflectionHelper for RefCell<T> {
+    const ENTRY_COUNT: usize = T::ENTRY_COUNT;
+    const HAS_NAMES: bool = T::HAS_NAMES;
+    const IS_LEAF: bool = T::IS_LEAF;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        T::field_infos()
+    }
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        match self.try_borrow() {
+            Ok(value) => value.instance_field_infos(),
+            Err(_) => T::field_infos(),
+        }
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value(self)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        T::read_row(reader, prefix).map(RefCell::new)
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        T::walk_type(walker, key);
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_leaf(walker, key, self);
+    }
+}
+
+impl<T: StructReflectionHelper> sealed::Sealed for Mutex<T> {}
+
+impl<T: StructReflectionHelper> StructReflectionHelper for Mutex<T> {
+    const ENTRY_COUNT: usize = T::ENTRY_COUNT;
+    const HAS_NAMES: bool = T::HAS_NAMES;
+    const IS_LEAF: bool = T::IS_LEAF;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        T::field_infos()
+    }
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        match self.try_lock() {
+            Ok(value) => value.instance_field_infos(),
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().instance_field_infos(),
+            Err(TryLockError::WouldBlock) => T::field_infos(),
+        }
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value(self)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        T::read_row(reader, prefix).map(Mutex::new)
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        T::walk_type(walker, key);
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_leaf(walker, key, self);
+    }
+}
+
+impl<T: StructReflectionHelper> sealed::Sealed for RwLock<T> {}
+
+impl<T: StructReflectionHelper> StructReflectionHelper for RwLock<T> {
+    const ENTRY_COUNT: usize = T::ENTRY_COUNT;
+    const HAS_NAMES: bool = T::HAS_NAMES;
+    const IS_LEAF: bool = T::IS_LEAF;
+
+    fn field_infos() -> Option<Vec<FieldInfo>> {
+        T::field_infos()
+    }
+
+    fn instance_field_infos(&self) -> Option<Vec<FieldInfo>> {
+        match self.try_read() {
+            Ok(value) => value.instance_field_infos(),
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().instance_field_infos(),
+            Err(TryLockError::WouldBlock) => T::field_infos(),
+        }
+    }
+
+    fn field_values(&self) -> Option<Vec<FieldRef<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value(self)
+    }
+
+    fn field_values_mut(&mut self) -> Option<Vec<FieldMut<'_>>>
+    where
+        Self: 'static,
+    {
+        leaf_value_mut(self)
+    }
+
+    fn read_row(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Self>
+    where
+        Self: 'static,
+    {
+        T::read_row(reader, prefix).map(RwLock::new)
+    }
+
+    fn read_boxed(reader: &mut RowReader<'_>, prefix: FieldPath) -> Option<Box<Self>>
+    where
+        Self: 'static,
+    {
+        Self::read_row(reader, prefix).map(Box::new)
+    }
+
+    fn walk_type(walker: &mut FieldWalker<'_>, key: FieldKey<'_>) {
+        T::walk_type(walker, key);
+    }
+
+    fn walk_value(&self, walker: &mut FieldWalker<'_>, key: FieldKey<'_>)
+    where
+        Self: 'static,
+    {
+        walk_leaf(walker, key, self);
+    }
 }
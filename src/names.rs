@@ -0,0 +1,46 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+type Names = Option<&'static [&'static str]>;
+
+/// Names cached by type, for the types the derive macro cannot give a `static`
+/// of their own: those with type or const parameters.
+static CACHE: OnceLock<RwLock<HashMap<TypeId, Names>>> = OnceLock::new();
+
+/// Moves computed names to the heap for the rest of the program.
+#[doc(hidden)]
+pub fn leak_names(names: Option<Vec<String>>) -> Names {
+    names.map(|names| {
+        let names: Vec<&'static str> = names
+            .into_iter()
+            .map(|name| &*Box::leak(name.into_boxed_str()))
+            .collect();
+        &*Box::leak(names.into_boxed_slice())
+    })
+}
+
+/// The names of the type identified by `type_id`, computed by `compute` the
+/// first time they are asked for.
+pub(crate) fn cached_names(
+    type_id: TypeId,
+    compute: impl FnOnce() -> Option<Vec<String>>,
+) -> Names {
+    let cache = CACHE.get_or_init(Default::default);
+
+    if let Some(&names) = cache
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(&type_id)
+    {
+        return names;
+    }
+
+    // Computed without holding the lock, as it may need the names of other types
+    let names = leak_names(compute());
+    *cache
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .entry(type_id)
+        .or_insert(names)
+}
@@ -0,0 +1,374 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// One step in the path to a reflected field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Segment {
+    /// A named struct field or enum variant.
+    Named(String),
+    /// A position in a tuple or tuple struct.
+    TupleIndex(usize),
+    /// A position in an array.
+    ArrayIndex(usize),
+    /// The marker added for the contents of an `Option<T>`.
+    OptionalMarker,
+    /// Any element of a collection, for fields marked `#[reflect(elements)]`.
+    Element,
+    /// Any key of a map, for fields marked `#[reflect(values)]`.
+    AnyKey,
+    /// A key of a map, as found in a live value.
+    Key(String),
+    /// Stands for the fields of the named type, which is already being
+    /// expanded further up the path.
+    Ref(String),
+}
+
+impl Segment {
+    /// The segment as it appears in the names returned by `struct_reflection()`.
+    pub fn to_flat_string(&self) -> String {
+        match self {
+            Segment::Named(name) => name.clone(),
+            Segment::TupleIndex(index) | Segment::ArrayIndex(index) => index.to_string(),
+            Segment::OptionalMarker => "optional".to_string(),
+            Segment::Element => "[]".to_string(),
+            Segment::AnyKey => "{}".to_string(),
+            Segment::Key(key) => key.clone(),
+            Segment::Ref(name) => format!("$ref:{name}"),
+        }
+    }
+}
+
+/// The path to a reflected field, made of typed segments.
+///
+/// Unlike the joined names returned by `struct_reflection()`, a `FieldPath`
+/// keeps a field literally named `a__b` apart from the nested `a` → `b`, and
+/// array indices apart from tuple positions.
+///
+/// Its `Display` form is unambiguous and can be parsed back with [`str::parse`]:
+/// named segments are separated by `.`, tuple positions are written `.0`,
+/// array indices `[0]`, collection elements `[]`, map keys `["key"]` (or `{}`
+/// for any key), the optional marker `?` and references to a recursive type
+/// `.$ref:Node`. Names that are not plain identifiers are quoted, as in
+/// `."e-mail"`.
+///
+/// ```
+/// use struct_reflection::{FieldPath, Segment};
+///
+/// let path: FieldPath = "items[1].0.name?".parse().unwrap();
+/// assert_eq!(
+///     path.segments(),
+///     &[
+///         Segment::Named("items".to_string()),
+///         Segment::ArrayIndex(1),
+///         Segment::TupleIndex(0),
+///         Segment::Named("name".to_string()),
+///         Segment::OptionalMarker,
+///     ]
+/// );
+/// assert_eq!(path.to_string(), "items[1].0.name?");
+/// assert_eq!(path.join("__"), "items__1__0__name__optional");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FieldPath {
+    segments: Vec<Segment>,
+}
+
+impl FieldPath {
+    pub fn new() -> Self {
+        FieldPath::default()
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn push(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    /// A copy of this path extended by one segment.
+    pub fn child(&self, segment: Segment) -> FieldPath {
+        let mut path = self.clone();
+        path.push(segment);
+        path
+    }
+
+    /// The path in the flattened form returned by `struct_reflection()`,
+    /// with segments joined by `separator`.
+    pub fn join(&self, separator: &str) -> String {
+        self.segments
+            .iter()
+            .map(Segment::to_flat_string)
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+}
+
+impl From<Vec<Segment>> for FieldPath {
+    fn from(segments: Vec<Segment>) -> Self {
+        FieldPath { segments }
+    }
+}
+
+impl From<Segment> for FieldPath {
+    fn from(segment: Segment) -> Self {
+        FieldPath {
+            segments: vec![segment],
+        }
+    }
+}
+
+impl FromIterator<Segment> for FieldPath {
+    fn from_iter<I: IntoIterator<Item = Segment>>(iter: I) -> Self {
+        FieldPath {
+            segments: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<Segment> for FieldPath {
+    fn extend<I: IntoIterator<Item = Segment>>(&mut self, iter: I) {
+        self.segments.extend(iter);
+    }
+}
+
+impl IntoIterator for FieldPath {
+    type Item = Segment;
+    type IntoIter = std::vec::IntoIter<Segment>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a FieldPath {
+    type Item = &'a Segment;
+    type IntoIter = std::slice::Iter<'a, Segment>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments.iter()
+    }
+}
+
+// Names that could be mistaken for indices or contain path punctuation are quoted
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit() || c == '$')
+        && name.chars().all(|c| {
+            !matches!(c, '.' | '[' | ']' | '{' | '}' | '?' | '"' | '\\') && !c.is_whitespace()
+        })
+}
+
+fn write_quoted(f: &mut fmt::Formatter<'_>, name: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in name.chars() {
+        if matches!(c, '"' | '\\') {
+            f.write_str("\\")?;
+        }
+        write!(f, "{c}")?;
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Named(_) | Segment::TupleIndex(_) | Segment::Ref(_) if i > 0 => {
+                    f.write_str(".")?
+                }
+                _ => {}
+            }
+
+            match segment {
+                Segment::Named(name) if is_plain_name(name) => f.write_str(name)?,
+                Segment::Named(name) => write_quoted(f, name)?,
+                Segment::TupleIndex(index) => write!(f, "{index}")?,
+                Segment::ArrayIndex(index) => write!(f, "[{index}]")?,
+                Segment::OptionalMarker => f.write_str("?")?,
+                Segment::Element => f.write_str("[]")?,
+                Segment::AnyKey => f.write_str("{}")?,
+                Segment::Ref(name) => write!(f, "$ref:{name}")?,
+                Segment::Key(key) => {
+                    f.write_str("[")?;
+                    write_quoted(f, key)?;
+                    f.write_str("]")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Error returned when parsing a [`FieldPath`] from a malformed string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFieldPathError {
+    position: usize,
+    message: &'static str,
+}
+
+impl ParseFieldPathError {
+    /// Byte offset in the input at which parsing failed.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for ParseFieldPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid field path at position {}: {}",
+            self.position, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseFieldPathError {}
+
+impl FromStr for FieldPath {
+    type Err = ParseFieldPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<(usize, char)> = s.char_indices().collect();
+        let error = |i: usize, message| ParseFieldPathError {
+            position: chars.get(i).map_or(s.len(), |&(position, _)| position),
+            message,
+        };
+
+        let mut segments = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i].1 {
+                '?' => {
+                    segments.push(Segment::OptionalMarker);
+                    i += 1;
+                    continue;
+                }
+                '[' if chars.get(i + 1).map(|&(_, c)| c) == Some(']') => {
+                    segments.push(Segment::Element);
+                    i += 2;
+                    continue;
+                }
+                '[' if chars.get(i + 1).map(|&(_, c)| c) == Some('"') => {
+                    let (key, end) =
+                        parse_quoted(&chars, i + 1).map_err(|(i, message)| error(i, message))?;
+                    if chars.get(end).map(|&(_, c)| c) != Some(']') {
+                        return Err(error(end, "expected `]` after a map key"));
+                    }
+                    segments.push(Segment::Key(key));
+                    i = end + 1;
+                    continue;
+                }
+                '{' => {
+                    if chars.get(i + 1).map(|&(_, c)| c) != Some('}') {
+                        return Err(error(i + 1, "expected `}`"));
+                    }
+                    segments.push(Segment::AnyKey);
+                    i += 2;
+                    continue;
+                }
+                '[' => {
+                    let start = i + 1;
+                    let mut end = start;
+                    while end < chars.len() && chars[end].1.is_ascii_digit() {
+                        end += 1;
+                    }
+                    if end == start || chars.get(end).map(|&(_, c)| c) != Some(']') {
+                        return Err(error(end, "expected an array index followed by `]`"));
+                    }
+                    let digits: String = chars[start..end].iter().map(|&(_, c)| c).collect();
+                    let index = digits
+                        .parse()
+                        .map_err(|_| error(start, "array index out of range"))?;
+                    segments.push(Segment::ArrayIndex(index));
+                    i = end + 1;
+                    continue;
+                }
+                '.' if !segments.is_empty() => i += 1,
+                _ if segments.is_empty() => {}
+                _ => return Err(error(i, "expected `.`, `[`, `{` or `?`")),
+            }
+
+            // A named segment or tuple position, after an optional `.`
+            match chars.get(i).map(|&(_, c)| c) {
+                None => return Err(error(i, "expected a segment after `.`")),
+                Some('"') => {
+                    let (name, end) =
+                        parse_quoted(&chars, i).map_err(|(i, message)| error(i, message))?;
+                    segments.push(Segment::Named(name));
+                    i = end;
+                }
+                Some(c) if c.is_ascii_digit() => {
+                    let start = i;
+                    while i < chars.len() && chars[i].1.is_ascii_digit() {
+                        i += 1;
+                    }
+                    let digits: String = chars[start..i].iter().map(|&(_, c)| c).collect();
+                    let index = digits
+                        .parse()
+                        .map_err(|_| error(start, "tuple index out of range"))?;
+                    segments.push(Segment::TupleIndex(index));
+                }
+                Some(_) => {
+                    let start = i;
+                    while i < chars.len() && !matches!(chars[i].1, '.' | '[' | '{' | '?') {
+                        i += 1;
+                    }
+                    let name: String = chars[start..i].iter().map(|&(_, c)| c).collect();
+                    if let Some(type_name) = name.strip_prefix("$ref:") {
+                        if !is_plain_name(type_name) {
+                            return Err(error(start, "invalid type name in reference"));
+                        }
+                        segments.push(Segment::Ref(type_name.to_string()));
+                        continue;
+                    }
+                    if !is_plain_name(&name) {
+                        return Err(error(start, "names with special characters must be quoted"));
+                    }
+                    segments.push(Segment::Named(name));
+                }
+            }
+        }
+
+        Ok(FieldPath { segments })
+    }
+}
+
+/// Parses the quoted string starting at `chars[start]`, returning its contents
+/// and the index just past the closing quote.
+fn parse_quoted(
+    chars: &[(usize, char)],
+    start: usize,
+) -> Result<(String, usize), (usize, &'static str)> {
+    let mut name = String::new();
+    let mut i = start + 1;
+
+    loop {
+        match chars.get(i).map(|&(_, c)| c) {
+            None => return Err((i, "unterminated quoted name")),
+            Some('"') => return Ok((name, i + 1)),
+            Some('\\') => {
+                match chars.get(i + 1).map(|&(_, c)| c) {
+                    Some(c @ ('"' | '\\')) => name.push(c),
+                    _ => return Err((i, "invalid escape in quoted name")),
+                }
+                i += 2;
+            }
+            Some(c) => {
+                name.push(c);
+                i += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::{FieldPath, Segment, Value};
+
+/// A column whose value cannot be converted to its field's type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MistypedColumn {
+    pub column: String,
+    /// The field's type, as given by [`std::any::type_name`].
+    pub expected: &'static str,
+    pub found: Value,
+}
+
+/// Error returned when rebuilding a value from a row, listing every column at
+/// fault.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FromRowError {
+    /// Columns the value needs that the row does not have.
+    pub missing: Vec<String>,
+    /// Columns the row has that are not among the value's names.
+    pub extra: Vec<String>,
+    pub mistyped: Vec<MistypedColumn>,
+    /// Skipped fields whose type has no `Default` value, which no column can
+    /// supply.
+    pub no_default: Vec<String>,
+}
+
+impl FromRowError {
+    fn is_empty(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.mistyped.is_empty()
+            && self.no_default.is_empty()
+    }
+}
+
+impl fmt::Display for FromRowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut problems = Vec::new();
+        if !self.missing.is_empty() {
+            problems.push(format!("missing columns {}", quoted(&self.missing)));
+        }
+        if !self.extra.is_empty() {
+            problems.push(format!("unknown columns {}", quoted(&self.extra)));
+        }
+        for column in &self.mistyped {
+            problems.push(format!(
+                "column `{}` holds {:?}, which is not a `{}`",
+                column.column, column.found, column.expected
+            ));
+        }
+        if !self.no_default.is_empty() {
+            problems.push(format!(
+                "no default value for skipped fields {}",
+                quoted(&self.no_default)
+            ));
+        }
+
+        if problems.is_empty() {
+            f.write_str("invalid row")
+        } else {
+            write!(f, "invalid row: {}", problems.join("; "))
+        }
+    }
+}
+
+impl Error for FromRowError {}
+
+fn quoted(columns: &[String]) -> String {
+    columns
+        .iter()
+        .map(|column| format!("`{column}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Where an option started being read; see [`RowReader::begin_option`].
+#[doc(hidden)]
+pub struct OptionCheckpoint {
+    filled: usize,
+    missing: usize,
+    no_default: usize,
+    mistyped: usize,
+}
+
+/// The columns of a row being rebuilt into a value, read by the code the derive
+/// macro generates.
+///
+/// Reads take the path of a leaf and look its column up by name, recording the
+/// columns that are missing or mistyped instead of stopping at the first one.
+#[doc(hidden)]
+pub struct RowReader<'a> {
+    columns: &'a HashMap<String, Value>,
+    separator: &'a str,
+    /// Optional markers appended to every name, one per `suffix` option being
+    /// read.
+    suffix: usize,
+    /// Derived types being read, outermost first.
+    stack: Vec<&'static str>,
+    /// How many of the columns read hold a value.
+    filled: usize,
+    error: FromRowError,
+}
+
+impl<'a> RowReader<'a> {
+    pub(crate) fn new(columns: &'a HashMap<String, Value>, separator: &'a str) -> Self {
+        RowReader {
+            columns,
+            separator,
+            suffix: 0,
+            stack: Vec::new(),
+            filled: 0,
+            error: FromRowError::default(),
+        }
+    }
+
+    /// The value read, or every column at fault.
+    pub(crate) fn finish<T>(
+        mut self,
+        value: Option<T>,
+        extra: Vec<String>,
+    ) -> Result<T, FromRowError> {
+        self.error.extra = extra;
+        match value {
+            Some(value) if self.error.is_empty() => Ok(value),
+            _ => Err(self.error),
+        }
+    }
+
+    /// The name of the column holding the leaf at `path`, as
+    /// `struct_reflection()` names it.
+    fn name(&self, path: &FieldPath) -> String {
+        let mut segments = path.segments().to_vec();
+        segments.extend((0..self.suffix).map(|_| Segment::OptionalMarker));
+        FieldPath::from(segments).join(self.separator)
+    }
+
+    /// The column holding the leaf at `path`, or its name if the row has none.
+    fn column(&mut self, path: &FieldPath) -> Result<(String, &'a Value), String> {
+        let name = self.name(path);
+        match self.columns.get(&name) {
+            Some(value) => {
+                if !value.is_null() {
+                    self.filled += 1;
+                }
+                Ok((name, value))
+            }
+            None => Err(name),
+        }
+    }
+
+    fn mistyped(&mut self, column: String, expected: &'static str, found: &Value) {
+        self.error.mistyped.push(MistypedColumn {
+            column,
+            expected,
+            found: found.clone(),
+        });
+    }
+
+    /// Reads a leaf of one of the types [`Value`] converts to.
+    pub fn leaf<T: Any>(&mut self, path: FieldPath) -> Option<T> {
+        match self.column(&path) {
+            Ok((name, value)) => {
+                let leaf = value.to_leaf::<T>();
+                if leaf.is_none() {
+                    self.mistyped(name, std::any::type_name::<T>(), value);
+                }
+                leaf
+            }
+            Err(name) => {
+                self.error.missing.push(name);
+                None
+            }
+        }
+    }
+
+    /// Reads an option that is a single leaf: a missing or null column is
+    /// `None`.
+    pub fn optional_leaf<T: Any>(&mut self, path: FieldPath) -> Option<Option<T>> {
+        match self.column(&path) {
+            Ok((name, value)) if !value.is_null() => {
+                let leaf = value.to_leaf::<T>();
+                if leaf.is_none() {
+                    self.mistyped(name, std::any::type_name::<Option<T>>(), value);
+                }
+                leaf.map(Some)
+            }
+            _ => Some(None),
+        }
+    }
+
+    /// Reads a leaf of a type that cannot be rebuilt from a value, recording
+    /// its column as mistyped.
+    pub fn unsupported<T>(&mut self, path: FieldPath, type_name: &'static str) -> Option<T> {
+        match self.column(&path) {
+            Ok((name, value)) => self.mistyped(name, type_name, value),
+            Err(name) => self.error.missing.push(name),
+        }
+        None
+    }
+
+    /// Reads a collection, whose contents are not columns of the row: it is
+    /// empty if all the columns named after its type, at `paths`, are null.
+    pub fn empty<T: Default>(
+        &mut self,
+        paths: impl IntoIterator<Item = FieldPath>,
+        type_name: &'static str,
+    ) -> Option<T> {
+        let mut empty = true;
+        for path in paths {
+            match self.column(&path) {
+                Ok((_, value)) if value.is_null() => {}
+                Ok((name, value)) => {
+                    self.mistyped(name, type_name, value);
+                    empty = false;
+                }
+                Err(name) => {
+                    self.error.missing.push(name);
+                    empty = false;
+                }
+            }
+        }
+        empty.then(T::default)
+    }
+
+    /// Whether the column of a variant named by its path holds a value.
+    pub fn marker(&mut self, path: FieldPath) -> bool {
+        matches!(self.column(&path), Ok((_, value)) if !value.is_null())
+    }
+
+    /// Reads the tag of a `union` enum, returning the index of the variant it
+    /// names.
+    pub fn tag(
+        &mut self,
+        path: FieldPath,
+        variant_names: &[&str],
+        type_name: &'static str,
+    ) -> Option<usize> {
+        let (name, value) = match self.column(&path) {
+            Ok(column) => column,
+            Err(name) => {
+                self.error.missing.push(name);
+                return None;
+            }
+        };
+
+        let index = match value {
+            Value::String(tag) => variant_names.iter().position(|name| name == tag),
+            _ => None,
+        };
+        if index.is_none() {
+            self.mistyped(name, type_name, value);
+        }
+        index
+    }
+
+    /// Records that no variant of the enum at `path` holds a value.
+    pub fn no_variant<T>(&mut self, path: FieldPath, type_name: &str) -> Option<T> {
+        let column = if path.is_empty() {
+            type_name.to_string()
+        } else {
+            self.name(&path)
+        };
+        self.error.missing.push(column);
+        None
+    }
+
+    /// Records a skipped field whose type has no `Default` value to take.
+    pub fn skipped<T>(&mut self, path: FieldPath, default: Option<T>) -> Option<T> {
+        if default.is_none() {
+            let name = self.name(&path);
+            self.error.no_default.push(name);
+        }
+        default
+    }
+
+    /// Starts reading an option, or a variant of an enum, which holds no value
+    /// if none of its columns do.
+    pub fn begin_option(&mut self) -> OptionCheckpoint {
+        let checkpoint = OptionCheckpoint {
+            filled: self.filled,
+            missing: self.error.missing.len(),
+            no_default: self.error.no_default.len(),
+            mistyped: self.error.mistyped.len(),
+        };
+        self.filled = 0;
+        checkpoint
+    }
+
+    /// Finishes reading an option: `Some(None)` if none of its columns hold a
+    /// value, in which case they are not at fault.
+    pub fn end_option<T>(
+        &mut self,
+        checkpoint: OptionCheckpoint,
+        value: Option<T>,
+    ) -> Option<Option<T>> {
+        let filled = self.filled;
+        self.filled += checkpoint.filled;
+
+        if filled == 0 {
+            self.error.missing.truncate(checkpoint.missing);
+            self.error.no_default.truncate(checkpoint.no_default);
+            self.error.mistyped.truncate(checkpoint.mistyped);
+            Some(None)
+        } else {
+            value.map(Some)
+        }
+    }
+
+    /// Reads the inner value of a `suffix` option, whose names end with the
+    /// optional marker.
+    pub fn suffixed<T>(&mut self, read: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        self.suffix += 1;
+        let value = read(self);
+        self.suffix -= 1;
+        value
+    }
+
+    /// Reads a derived type, unless it is already being read further up: a
+    /// recursive type is then the single `$ref:Name` entry it is named by.
+    pub fn enter<T>(
+        &mut self,
+        type_name: &'static str,
+        name: &str,
+        prefix: FieldPath,
+        read: impl FnOnce(&mut Self, FieldPath) -> Option<T>,
+    ) -> Option<T> {
+        if self.stack.contains(&type_name) {
+            return self.unsupported(prefix.child(Segment::Ref(name.to_string())), type_name);
+        }
+
+        self.stack.push(type_name);
+        let value = read(self, prefix);
+        self.stack.pop();
+        value
+    }
+}
+
+/// The value of a skipped field: its type's `Default` value if it has one.
+///
+/// Used as `(&SkippedField::<T>::new()).default_value()`, which resolves to
+/// [`SkippedDefault`] when `T: Default` and to [`SkippedNoDefault`] otherwise.
+#[doc(hidden)]
+pub struct SkippedField<T>(PhantomData<T>);
+
+impl<T> SkippedField<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        SkippedField(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait SkippedDefault<T> {
+    fn default_value(&self) -> Option<T>;
+}
+
+impl<T: Default> SkippedDefault<T> for SkippedField<T> {
+    fn default_value(&self) -> Option<T> {
+        Some(T::default())
+    }
+}
+
+#[doc(hidden)]
+pub trait SkippedNoDefault<T> {
+    fn default_value(&self) -> Option<T>;
+}
+
+impl<T> SkippedNoDefault<T> for &SkippedField<T> {
+    fn default_value(&self) -> Option<T> {
+        None
+    }
+}
@@ -0,0 +1,178 @@
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+    NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::PrimitiveKind;
+
+/// The value of one reflected leaf, as an entry of a row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Signed integers.
+    Int(i64),
+    /// Unsigned integers.
+    UInt(u64),
+    /// `f32` and `f64`.
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    /// `String` and `str`, behind any pointer.
+    String(String),
+    /// A column that holds no value, such as the fields of an option holding
+    /// `None`.
+    Null,
+    /// A leaf of any other type, named by [`std::any::type_name`].
+    Opaque(&'static str),
+}
+
+impl Value {
+    /// Whether this is [`Value::Null`].
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    // `type_name` names the leaf's type when it is none of the known ones
+    pub(crate) fn from_any(value: &dyn Any, type_name: &'static str) -> Self {
+        macro_rules! convert {
+            ($($variant:ident($target:ty): $($ty:ty),* $(| $($non_zero:ty),*)?;)*) => {
+                $(
+                    $(if let Some(value) = value.downcast_ref::<$ty>() {
+                        return Value::$variant(<$target>::from(*value));
+                    })*
+                    $($(if let Some(value) = value.downcast_ref::<$non_zero>() {
+                        return Value::$variant(<$target>::from(value.get()));
+                    })*)?
+                )*
+            };
+        }
+
+        convert! {
+            Int(i64): i8, i16, i32, i64 | NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64;
+            UInt(u64): u8, u16, u32, u64 | NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64;
+            Float(f64): f32, f64;
+            Bool(bool): bool;
+            Char(char): char;
+        }
+
+        // Integers without a lossless conversion into the 64-bit ones
+        if let Some(int) = downcast_int(value) {
+            return int;
+        }
+
+        if let Some(string) = downcast_str(value) {
+            return Value::String(string.to_string());
+        }
+
+        Value::Opaque(type_name)
+    }
+
+    /// Converts the value back into a leaf of type `T`, if `T` is one of the
+    /// leaf types a row can hold and the value is of a matching kind.
+    ///
+    /// Strings are parsed, so that rows read from text files can be converted.
+    pub(crate) fn to_leaf<T: Any>(&self) -> Option<T> {
+        macro_rules! convert {
+            ($($kind:ident => $($ty:ty),+;)*) => {
+                $($(
+                    if TypeId::of::<T>() == TypeId::of::<$ty>() {
+                        let mut leaf = self.parse::<$ty>(PrimitiveKind::$kind);
+                        return (&mut leaf as &mut dyn Any)
+                            .downcast_mut::<Option<T>>()
+                            .and_then(Option::take);
+                    }
+                )+)*
+            };
+        }
+
+        convert! {
+            Int => u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize;
+            Int => NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128, NonZeroUsize;
+            Int => NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI128, NonZeroIsize;
+            Float => f32, f64;
+            Bool => bool;
+            Char => char;
+            String => String;
+            Other => PathBuf, IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6;
+        }
+
+        None
+    }
+
+    // Values other than strings go through their `Display` form, for the kinds
+    // of leaves they can stand for: integers can fill float fields, but not the
+    // other way around
+    fn parse<T: FromStr>(&self, kind: PrimitiveKind) -> Option<T> {
+        let matches = match (self, kind) {
+            (Value::String(string), _) => return string.parse().ok(),
+            (Value::Int(_) | Value::UInt(_), PrimitiveKind::Int | PrimitiveKind::Float) => true,
+            (Value::Float(_), PrimitiveKind::Float) => true,
+            (Value::Bool(_), PrimitiveKind::Bool) => true,
+            (Value::Char(_), PrimitiveKind::Char) => true,
+            _ => false,
+        };
+        if matches {
+            self.to_string().parse().ok()
+        } else {
+            None
+        }
+    }
+}
+
+fn downcast_int(value: &dyn Any) -> Option<Value> {
+    if let Some(value) = value.downcast_ref::<isize>() {
+        i64::try_from(*value).ok().map(Value::Int)
+    } else if let Some(value) = value.downcast_ref::<NonZeroIsize>() {
+        i64::try_from(value.get()).ok().map(Value::Int)
+    } else if let Some(value) = value.downcast_ref::<i128>() {
+        i64::try_from(*value).ok().map(Value::Int)
+    } else if let Some(value) = value.downcast_ref::<usize>() {
+        u64::try_from(*value).ok().map(Value::UInt)
+    } else if let Some(value) = value.downcast_ref::<NonZeroUsize>() {
+        u64::try_from(value.get()).ok().map(Value::UInt)
+    } else if let Some(value) = value.downcast_ref::<u128>() {
+        u64::try_from(*value).ok().map(Value::UInt)
+    } else {
+        None
+    }
+}
+
+fn downcast_str(value: &dyn Any) -> Option<&str> {
+    if let Some(string) = value.downcast_ref::<String>() {
+        Some(string)
+    } else if let Some(string) = value.downcast_ref::<&'static str>() {
+        Some(string)
+    } else if let Some(string) = value.downcast_ref::<Box<str>>() {
+        Some(string)
+    } else if let Some(string) = value.downcast_ref::<Rc<str>>() {
+        Some(string)
+    } else if let Some(string) = value.downcast_ref::<Arc<str>>() {
+        Some(string)
+    } else if let Some(string) = value.downcast_ref::<Cow<'static, str>>() {
+        Some(string)
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{value}"),
+            Value::UInt(value) => write!(f, "{value}"),
+            Value::Float(value) => write!(f, "{value}"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Char(value) => write!(f, "{value}"),
+            Value::String(value) => f.write_str(value),
+            Value::Null => Ok(()),
+            Value::Opaque(type_name) => write!(f, "<{type_name}>"),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::any::Any;
+
+use crate::{PrimitiveKind, Segment};
+
+/// Where a visited node sits in the one holding it: the borrowed counterpart
+/// of a [`Segment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldKey<'a> {
+    /// A named struct field or enum variant.
+    Named(&'a str),
+    /// A position in a tuple or tuple struct.
+    TupleIndex(usize),
+    /// A position in an array or collection.
+    ArrayIndex(usize),
+    /// The contents of an `Option<T>`.
+    Optional,
+    /// Any element of a collection, in walks of a type.
+    Element,
+    /// Any value of a map, in walks of a type.
+    AnyKey,
+    /// A key of a map, as found in a live value.
+    Key(&'a str),
+    /// Stands for the fields of the named type, which is already being
+    /// visited further up.
+    Ref(&'a str),
+}
+
+impl FieldKey<'_> {
+    /// The segment the key adds to the paths of the leaves below it.
+    pub fn to_segment(self) -> Segment {
+        match self {
+            FieldKey::Named(name) => Segment::Named(name.to_string()),
+            FieldKey::TupleIndex(index) => Segment::TupleIndex(index),
+            FieldKey::ArrayIndex(index) => Segment::ArrayIndex(index),
+            FieldKey::Optional => Segment::OptionalMarker,
+            FieldKey::Element => Segment::Element,
+            FieldKey::AnyKey => Segment::AnyKey,
+            FieldKey::Key(key) => Segment::Key(key.to_string()),
+            FieldKey::Ref(name) => Segment::Ref(name.to_string()),
+        }
+    }
+}
+
+/// Where the marker of an option goes in the names of its contents, as set by
+/// the `option` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptionStyle {
+    /// At the end of each name: `address__street__optional`.
+    Suffix,
+    /// Right after the option's own segment: `address__optional__street`.
+    Prefix,
+    /// The whole option is a single leaf: `address__optional`.
+    Collapse,
+}
+
+/// A leaf reached by a [`FieldVisitor`], described as in a
+/// [`FieldInfo`](crate::FieldInfo).
+#[derive(Debug, Clone, Copy)]
+pub struct Leaf<'a> {
+    /// The leaf's type, as given by [`std::any::type_name`]. In walks of a
+    /// value, an unsized leaf is borrowed through its pointer, whose type this
+    /// is.
+    pub type_name: &'static str,
+    pub kind: PrimitiveKind,
+    /// Whether the leaf's type is one of the deriving type's generic parameters.
+    pub is_generic: bool,
+    /// The leaf itself in walks of a value, `None` in walks of a type.
+    pub value: Option<&'a dyn Any>,
+}
+
+/// Callbacks for the walks of [`struct_reflection_visit`](crate::StructReflection::struct_reflection_visit)
+/// and [`struct_reflection_visit_values`](crate::StructReflection::struct_reflection_visit_values).
+///
+/// Every node comes with the key it sits under in its parent, and every
+/// `enter_*` call is followed by the calls for the node's contents, then by
+/// [`exit`](FieldVisitor::exit). The walked type itself and flattened fields
+/// are not entered: their contents are visited in place, as their names are
+/// inlined into their parent's. Leaves are visited in the order of the names
+/// of [`struct_reflection`](crate::StructReflection::struct_reflection).
+///
+/// Keys are the segments of the names: a field with a `prefix` is keyed by
+/// its prefix when its contents nest below it. The contents of an option are
+/// keyed [`FieldKey::Optional`] whatever its [`OptionStyle`], which
+/// [`enter_option`](FieldVisitor::enter_option) reports so that the marker can
+/// be moved to the end of the names of `Suffix` options.
+///
+/// ```
+/// use struct_reflection::{FieldKey, FieldVisitor, Leaf, OptionStyle, StructReflection};
+///
+/// #[derive(StructReflection)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// #[derive(StructReflection)]
+/// struct Shape {
+///     origin: Point,
+///     label: Option<String>,
+/// }
+///
+/// // Lists the leaves, indented by depth
+/// #[derive(Default)]
+/// struct Outline {
+///     depth: usize,
+///     lines: Vec<String>,
+/// }
+///
+/// impl FieldVisitor for Outline {
+///     fn enter_struct(&mut self, key: FieldKey<'_>, _type_name: &'static str) {
+///         let name = key.to_segment().to_flat_string();
+///         self.lines.push(format!("{}{name}", "  ".repeat(self.depth)));
+///         self.depth += 1;
+///     }
+///
+///     fn enter_option(&mut self, key: FieldKey<'_>, _style: OptionStyle) {
+///         self.enter_struct(key, "");
+///     }
+///
+///     fn leaf(&mut self, key: FieldKey<'_>, leaf: Leaf<'_>) {
+///         let name = key.to_segment().to_flat_string();
+///         let indent = "  ".repeat(self.depth);
+///         self.lines.push(format!("{indent}{name}: {}", leaf.type_name));
+///     }
+///
+///     fn exit(&mut self) {
+///         self.depth -= 1;
+///     }
+/// }
+///
+/// let mut outline = Outline::default();
+/// Shape::struct_reflection_visit(&mut outline);
+/// assert_eq!(
+///     outline.lines,
+///     [
+///         "origin",
+///         "  x: i32",
+///         "  y: i32",
+///         "label",
+///         "  optional: alloc::string::String",
+///     ]
+/// );
+/// ```
+#[allow(unused_variables)]
+pub trait FieldVisitor {
+    /// A derived struct or enum, or a variant of an enum that holds fields.
+    fn enter_struct(&mut self, key: FieldKey<'_>, type_name: &'static str) {}
+
+    /// An array, or a `#[reflect(elements)]` collection. The length of a
+    /// collection is only known in walks of a value: its element is keyed
+    /// [`FieldKey::Element`] in walks of a type.
+    fn enter_array(&mut self, key: FieldKey<'_>, len: Option<usize>) {}
+
+    fn enter_tuple(&mut self, key: FieldKey<'_>, len: usize) {}
+
+    /// An option, whose contents are keyed [`FieldKey::Optional`] and named
+    /// following `style`. In walks of a value, an option holding `None` has no
+    /// contents.
+    fn enter_option(&mut self, key: FieldKey<'_>, style: OptionStyle) {}
+
+    /// A `#[reflect(values)]` map, whose values are keyed [`FieldKey::AnyKey`]
+    /// in walks of a type and [`FieldKey::Key`] in walks of a value.
+    fn enter_map(&mut self, key: FieldKey<'_>, len: Option<usize>) {}
+
+    fn leaf(&mut self, key: FieldKey<'_>, leaf: Leaf<'_>) {}
+
+    /// Leaves the node entered last.
+    fn exit(&mut self) {}
+}
+
+/// Drives a [`FieldVisitor`] through the code the derive macro generates.
+#[doc(hidden)]
+pub struct FieldWalker<'a> {
+    visitor: &'a mut dyn FieldVisitor,
+    /// Whether the next node is visited in place, for the walked type itself
+    /// and flattened fields.
+    flatten: bool,
+    /// Derived types being visited by type, outermost first.
+    stack: Vec<&'static str>,
+}
+
+impl<'a> FieldWalker<'a> {
+    pub(crate) fn new(visitor: &'a mut dyn FieldVisitor) -> Self {
+        FieldWalker {
+            visitor,
+            flatten: true,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Visits a node entered by `enter`, unless it is visited in place.
+    fn node(&mut self, enter: impl FnOnce(&mut dyn FieldVisitor), visit: impl FnOnce(&mut Self)) {
+        if std::mem::take(&mut self.flatten) {
+            visit(self);
+        } else {
+            enter(&mut *self.visitor);
+            visit(self);
+            self.visitor.exit();
+        }
+    }
+
+    /// Visits a flattened field, whose contents are inlined into its parent.
+    pub fn flattened(&mut self, visit: impl FnOnce(&mut Self)) {
+        self.flatten = true;
+        visit(self);
+        self.flatten = false;
+    }
+
+    /// Visits a derived type by type, unless it is already being visited
+    /// further up: a recursive type then holds the single `$ref:Name` leaf it
+    /// is named by.
+    pub fn enter(
+        &mut self,
+        type_name: &'static str,
+        name: &str,
+        key: FieldKey<'_>,
+        visit: impl FnOnce(&mut Self),
+    ) {
+        if self.stack.contains(&type_name) {
+            self.visit_struct(key, type_name, |walker| {
+                walker.leaf(
+                    FieldKey::Ref(name),
+                    Leaf {
+                        type_name,
+                        kind: PrimitiveKind::Other,
+                        is_generic: false,
+                        value: None,
+                    },
+                );
+            });
+            return;
+        }
+
+        self.stack.push(type_name);
+        visit(self);
+        self.stack.pop();
+    }
+
+    pub fn visit_struct(
+        &mut self,
+        key: FieldKey<'_>,
+        type_name: &'static str,
+        visit: impl FnOnce(&mut Self),
+    ) {
+        self.node(|visitor| visitor.enter_struct(key, type_name), visit);
+    }
+
+    pub fn visit_array(
+        &mut self,
+        key: FieldKey<'_>,
+        len: Option<usize>,
+        visit: impl FnOnce(&mut Self),
+    ) {
+        self.node(|visitor| visitor.enter_array(key, len), visit);
+    }
+
+    pub fn visit_tuple(&mut self, key: FieldKey<'_>, len: usize, visit: impl FnOnce(&mut Self)) {
+        self.node(|visitor| visitor.enter_tuple(key, len), visit);
+    }
+
+    pub fn visit_option(
+        &mut self,
+        key: FieldKey<'_>,
+        style: OptionStyle,
+        visit: impl FnOnce(&mut Self),
+    ) {
+        self.node(|visitor| visitor.enter_option(key, style), visit);
+    }
+
+    pub fn visit_map(
+        &mut self,
+        key: FieldKey<'_>,
+        len: Option<usize>,
+        visit: impl FnOnce(&mut Self),
+    ) {
+        self.node(|visitor| visitor.enter_map(key, len), visit);
+    }
+
+    pub fn leaf(&mut self, key: FieldKey<'_>, leaf: Leaf<'_>) {
+        self.flatten = false;
+        self.visitor.leaf(key, leaf);
+    }
+}
@@ -0,0 +1,350 @@
+// Parsing of `#[reflect(...)]` attributes
+
+use syn::spanned::Spanned;
+
+use crate::case::RenameRule;
+
+/// How an enum's variants are turned into field names.
+#[derive(Clone, Copy, Default)]
+pub enum EnumStrategy {
+    /// One entry per variant name.
+    Variants,
+    /// Every variant's fields, prefixed by the variant name. Unit variants
+    /// contribute their bare name.
+    #[default]
+    Prefixed,
+    /// A discriminant column followed by the union of all variant fields.
+    Union,
+}
+
+/// How `Option<T>` fields mark the names of `T`.
+#[derive(Clone, Copy, Default)]
+pub enum OptionStyle {
+    /// `field__inner__optional`
+    #[default]
+    Suffix,
+    /// `field__optional__inner`
+    Prefix,
+    /// A single `field__optional` entry, regardless of `T`.
+    Collapse,
+}
+
+impl OptionStyle {
+    fn parse(value: &syn::LitStr) -> syn::Result<Self> {
+        match value.value().as_str() {
+            "suffix" => Ok(OptionStyle::Suffix),
+            "prefix" => Ok(OptionStyle::Prefix),
+            "collapse" => Ok(OptionStyle::Collapse),
+            _ => Err(syn::Error::new_spanned(
+                value,
+                "expected one of \"suffix\", \"prefix\" or \"collapse\"",
+            )),
+        }
+    }
+}
+
+/// Options set on the deriving type itself, e.g. `#[reflect(crate = "...")]`.
+#[derive(Default)]
+pub struct ContainerAttrs {
+    krate: Option<syn::Path>,
+    enum_strategy: EnumStrategy,
+    tag: Option<String>,
+    option_style: OptionStyle,
+    rename_all: Option<RenameRule>,
+    separator: Option<String>,
+    transparent: bool,
+    is_unsized: bool,
+}
+
+impl ContainerAttrs {
+    pub fn from_ast(input: &syn::DeriveInput) -> syn::Result<Self> {
+        let mut container_attrs = ContainerAttrs::default();
+        let is_enum = matches!(input.data, syn::Data::Enum(_));
+
+        for attr in &input.attrs {
+            if !attr.path().is_ident("reflect") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("crate") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    container_attrs.krate = Some(value.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("enum_strategy") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    if !is_enum {
+                        return Err(meta.error("`enum_strategy` can only be used on enums"));
+                    }
+                    container_attrs.enum_strategy = match value.value().as_str() {
+                        "variants" => EnumStrategy::Variants,
+                        "prefixed" => EnumStrategy::Prefixed,
+                        "union" => EnumStrategy::Union,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                value,
+                                "expected one of \"variants\", \"prefixed\" or \"union\"",
+                            ))
+                        }
+                    };
+                    Ok(())
+                } else if meta.path.is_ident("tag") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    if !is_enum {
+                        return Err(meta.error("`tag` can only be used on enums"));
+                    }
+                    container_attrs.tag = Some(value.value());
+                    Ok(())
+                } else if meta.path.is_ident("option") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    container_attrs.option_style = OptionStyle::parse(&value)?;
+                    Ok(())
+                } else if meta.path.is_ident("rename_all") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    container_attrs.rename_all = Some(RenameRule::parse(&value)?);
+                    Ok(())
+                } else if meta.path.is_ident("separator") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    if value.value().is_empty() {
+                        return Err(syn::Error::new_spanned(value, "separator cannot be empty"));
+                    }
+                    container_attrs.separator = Some(value.value());
+                    Ok(())
+                } else if meta.path.is_ident("transparent") {
+                    if !matches!(input.data, syn::Data::Struct(_)) {
+                        return Err(meta.error("`transparent` can only be used on structs"));
+                    }
+                    container_attrs.transparent = true;
+                    Ok(())
+                } else if meta.path.is_ident("unsized") {
+                    if !matches!(input.data, syn::Data::Struct(_)) {
+                        return Err(meta.error("`unsized` can only be used on structs"));
+                    }
+                    container_attrs.is_unsized = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported `reflect` container attribute"))
+                }
+            })?;
+        }
+
+        Ok(container_attrs)
+    }
+
+    /// Path used to refer to the runtime crate in generated code.
+    pub fn crate_path(&self) -> syn::Path {
+        self.krate
+            .clone()
+            .unwrap_or_else(|| syn::parse_quote!(::struct_reflection))
+    }
+
+    pub fn enum_strategy(&self) -> EnumStrategy {
+        self.enum_strategy
+    }
+
+    /// Name of the discriminant column used by the `union` strategy.
+    pub fn tag(&self) -> &str {
+        self.tag.as_deref().unwrap_or("variant")
+    }
+
+    pub fn option_style(&self) -> OptionStyle {
+        self.option_style
+    }
+
+    /// Separator joining the segments of the names returned by `struct_reflection()`.
+    pub fn separator(&self) -> Option<&str> {
+        self.separator.as_deref()
+    }
+
+    /// Whether the struct is reflected as its only field, without a segment
+    /// of its own.
+    pub fn transparent(&self) -> bool {
+        self.transparent
+    }
+
+    /// Whether the struct's last field is unsized, so that it cannot be
+    /// rebuilt from a row.
+    pub fn is_unsized(&self) -> bool {
+        self.is_unsized
+    }
+
+    /// Applies `rename_all`, if any, to a field or variant name.
+    pub fn rename(&self, name: &str) -> String {
+        match self.rename_all {
+            Some(rule) => rule.apply(name),
+            None => name.to_string(),
+        }
+    }
+}
+
+/// Options set on a single field, e.g. `#[reflect(option = "collapse")]`.
+#[derive(Default)]
+pub struct FieldAttrs {
+    option_style: Option<OptionStyle>,
+    rename: Option<String>,
+    skip: bool,
+    skip_if: Option<proc_macro2::TokenStream>,
+    elements: bool,
+    values: bool,
+    flatten: Option<proc_macro2::Span>,
+    prefix: Option<String>,
+    leaf: bool,
+}
+
+impl FieldAttrs {
+    pub fn from_ast(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut field_attrs = FieldAttrs::default();
+
+        for attr in attrs {
+            if !attr.path().is_ident("reflect") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("option") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    field_attrs.option_style = Some(OptionStyle::parse(&value)?);
+                    Ok(())
+                } else if meta.path.is_ident("rename") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    field_attrs.rename = Some(value.value());
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    field_attrs.skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("skip_if") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    field_attrs.skip_if = Some(content.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("elements") {
+                    field_attrs.elements = true;
+                    Ok(())
+                } else if meta.path.is_ident("values") {
+                    field_attrs.values = true;
+                    Ok(())
+                } else if meta.path.is_ident("flatten") {
+                    field_attrs.flatten = Some(meta.path.span());
+                    Ok(())
+                } else if meta.path.is_ident("leaf") {
+                    field_attrs.leaf = true;
+                    Ok(())
+                } else if meta.path.is_ident("prefix") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    if value.value().is_empty() {
+                        return Err(syn::Error::new_spanned(
+                            value,
+                            "prefix cannot be empty, use `flatten` to drop it",
+                        ));
+                    }
+                    field_attrs.prefix = Some(value.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported `reflect` field attribute"))
+                }
+            })?;
+        }
+
+        if let Some(span) = field_attrs.flatten {
+            if field_attrs.rename.is_some() {
+                return Err(syn::Error::new(
+                    span,
+                    "`flatten` cannot be combined with `rename`",
+                ));
+            }
+            if field_attrs.prefix.is_some() {
+                return Err(syn::Error::new(
+                    span,
+                    "`flatten` cannot be combined with `prefix`",
+                ));
+            }
+        }
+
+        Ok(field_attrs)
+    }
+
+    /// Overrides the container's `option` style for this field.
+    pub fn option_style(&self) -> Option<OptionStyle> {
+        self.option_style
+    }
+
+    /// Name set with `#[reflect(rename = "...")]`, taking precedence over `rename_all`.
+    pub fn rename(&self) -> Option<&str> {
+        self.rename.as_deref()
+    }
+
+    pub fn skip(&self) -> bool {
+        self.skip
+    }
+
+    /// `cfg` predicate under which the field is left out, as in
+    /// `#[reflect(skip_if(feature = "internal"))]`.
+    pub fn skip_if(&self) -> Option<&proc_macro2::TokenStream> {
+        self.skip_if.as_ref()
+    }
+
+    /// Whether collections in the field's type expose their element type as a
+    /// `[]` segment rather than being a single leaf.
+    pub fn elements(&self) -> bool {
+        self.elements
+    }
+
+    /// Whether maps in the field's type expose their value type under a key
+    /// segment rather than being a single leaf.
+    pub fn values(&self) -> bool {
+        self.values
+    }
+
+    /// Whether the field's own names are inlined into the parent, without the
+    /// field's segment.
+    pub fn flatten(&self) -> bool {
+        self.flatten.is_some()
+    }
+
+    /// Segment put in front of the names nested in the field, set with
+    /// `#[reflect(prefix = "...")]`. The field's own entry, if it is a leaf,
+    /// keeps the field's name.
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    /// Whether the field's type is a single entry, without asking it for
+    /// fields. Tuples, arrays and options around it are still expanded.
+    pub fn leaf(&self) -> bool {
+        self.leaf
+    }
+}
+
+/// Options set on an enum variant, e.g. `#[reflect(rename = "...")]`.
+#[derive(Default)]
+pub struct VariantAttrs {
+    rename: Option<String>,
+}
+
+impl VariantAttrs {
+    pub fn from_ast(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut variant_attrs = VariantAttrs::default();
+
+        for attr in attrs {
+            if !attr.path().is_ident("reflect") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    variant_attrs.rename = Some(value.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported `reflect` variant attribute"))
+                }
+            })?;
+        }
+
+        Ok(variant_attrs)
+    }
+
+    pub fn rename(&self) -> Option<&str> {
+        self.rename.as_deref()
+    }
+}
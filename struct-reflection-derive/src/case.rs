@@ -0,0 +1,107 @@
+// Case conversions for `#[reflect(rename_all = "...")]`
+
+/// A naming convention that field and variant names can be converted to.
+#[derive(Clone, Copy)]
+pub enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    pub fn parse(value: &syn::LitStr) -> syn::Result<Self> {
+        match value.value().as_str() {
+            "lowercase" => Ok(RenameRule::Lower),
+            "UPPERCASE" => Ok(RenameRule::Upper),
+            "PascalCase" => Ok(RenameRule::Pascal),
+            "camelCase" => Ok(RenameRule::Camel),
+            "snake_case" => Ok(RenameRule::Snake),
+            "SCREAMING_SNAKE_CASE" => Ok(RenameRule::ScreamingSnake),
+            "kebab-case" => Ok(RenameRule::Kebab),
+            "SCREAMING-KEBAB-CASE" => Ok(RenameRule::ScreamingKebab),
+            _ => Err(syn::Error::new_spanned(
+                value,
+                "expected one of \"lowercase\", \"UPPERCASE\", \"PascalCase\", \"camelCase\", \
+                 \"snake_case\", \"SCREAMING_SNAKE_CASE\", \"kebab-case\" or \"SCREAMING-KEBAB-CASE\"",
+            )),
+        }
+    }
+
+    /// Converts a `snake_case` field name or a `PascalCase` variant name.
+    ///
+    /// Leading underscores (as in `_phantom`) are kept as they are.
+    pub fn apply(self, name: &str) -> String {
+        let trimmed = name.trim_start_matches('_');
+        let leading = &name[..name.len() - trimmed.len()];
+        let words = split_words(trimmed);
+
+        let converted = match self {
+            RenameRule::Lower => trimmed.to_lowercase(),
+            RenameRule::Upper => trimmed.to_uppercase(),
+            RenameRule::Pascal => words.iter().map(|word| capitalize(word)).collect(),
+            RenameRule::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    if i == 0 {
+                        word.clone()
+                    } else {
+                        capitalize(word)
+                    }
+                })
+                .collect(),
+            RenameRule::Snake => words.join("_"),
+            RenameRule::ScreamingSnake => words.join("_").to_uppercase(),
+            RenameRule::Kebab => words.join("-"),
+            RenameRule::ScreamingKebab => words.join("-").to_uppercase(),
+        };
+
+        format!("{leading}{converted}")
+    }
+}
+
+/// Splits `snake_case`, `camelCase` and `PascalCase` names into lowercase words.
+fn split_words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if c.is_uppercase() && !current.is_empty() {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            // Split on `aB` and on the last capital of an acronym, as in `HTTPServer`
+            if !prev.is_uppercase() || next_is_lower {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+
+        current.extend(c.to_lowercase());
+    }
+
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -1,332 +1,2591 @@
 extern crate proc_macro;
 
+mod attr;
+mod case;
+
 use proc_macro::TokenStream;
 use quote::quote;
+use syn::ext::IdentExt;
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
 
-#[proc_macro_derive(StructReflection)]
-pub fn struct_reflection(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
+use attr::{ContainerAttrs, EnumStrategy, FieldAttrs, OptionStyle, VariantAttrs};
+
+#[proc_macro_derive(StructReflection, attributes(reflect))]
+pub fn struct_reflection(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_struct_reflection(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// State shared by the code generation functions.
+struct Context<'a> {
+    krate: syn::Path,
+    generic_types: Vec<&'a syn::TypeParam>,
+    container_attrs: &'a ContainerAttrs,
+}
+
+impl Context<'_> {
+    /// Name a field is reflected under, after `rename` and `rename_all`.
+    fn field_name(&self, index: usize, field: &syn::Field, field_attrs: &FieldAttrs) -> String {
+        if let Some(rename) = field_attrs.rename() {
+            return rename.to_string();
+        }
+        match &field.ident {
+            Some(ident) => self.container_attrs.rename(&ident.unraw().to_string()),
+            None => index.to_string(),
+        }
+    }
+
+    /// Expression building the first path segment of a field.
+    fn field_segment(
+        &self,
+        index: usize,
+        field: &syn::Field,
+        field_attrs: &FieldAttrs,
+    ) -> proc_macro2::TokenStream {
+        let krate = &self.krate;
+        if field.ident.is_none() && field_attrs.rename().is_none() {
+            return quote!(#krate::Segment::TupleIndex(#index));
+        }
+        self.named_segment(&self.field_name(index, field, field_attrs))
+    }
+
+    /// Expression building the `FieldKey` a field is visited under.
+    fn field_key(
+        &self,
+        index: usize,
+        field: &syn::Field,
+        field_attrs: &FieldAttrs,
+    ) -> proc_macro2::TokenStream {
+        let krate = &self.krate;
+        if field.ident.is_none() && field_attrs.rename().is_none() {
+            return quote!(#krate::FieldKey::TupleIndex(#index));
+        }
+        let name = self.field_name(index, field, field_attrs);
+        quote!(#krate::FieldKey::Named(#name))
+    }
+
+    fn named_segment(&self, name: &str) -> proc_macro2::TokenStream {
+        let krate = &self.krate;
+        quote!(#krate::Segment::Named(::std::string::String::from(#name)))
+    }
+
+    fn variant_name(&self, variant: &syn::Variant) -> syn::Result<String> {
+        let variant_attrs = VariantAttrs::from_ast(&variant.attrs)?;
+        Ok(match variant_attrs.rename() {
+            Some(rename) => rename.to_string(),
+            None => self
+                .container_attrs
+                .rename(&variant.ident.unraw().to_string()),
+        })
+    }
+
+    fn is_generic_type(&self, ty: &syn::Type) -> bool {
+        self.generic_types
+            .iter()
+            .any(|generic_type| is_generic_parameter(ty, generic_type))
+    }
+}
+
+fn expand_struct_reflection(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let container_attrs = ContainerAttrs::from_ast(&input)?;
+    let ctx = Context {
+        krate: container_attrs.crate_path(),
+        generic_types: input.generics.type_params().collect(),
+        container_attrs: &container_attrs,
+    };
+
+    let struct_name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (
+        field_list_code,
+        instance_code,
+        values_code,
+        values_mut_code,
+        field_count,
+        has_names,
+        is_leaf,
+        read_code,
+        (visit_code, visit_value_code),
+    ) = match &input.data {
+        Data::Struct(data_struct) => {
+            check_field_types(&data_struct.fields)?;
+            if container_attrs.transparent() {
+                check_transparent(&input.ident, &data_struct.fields)?;
+            }
+            let bindings = |walk: Walk| -> Vec<_> {
+                let reference = walk.reference();
+                data_struct
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, field)| {
+                        let member = match &field.ident {
+                            Some(ident) => quote!(#ident),
+                            None => {
+                                let index = syn::Index::from(i);
+                                quote!(#index)
+                            }
+                        };
+                        quote!(#reference self.#member)
+                    })
+                    .collect()
+            };
+            (
+                generate_fields_code(&data_struct.fields, Walk::Type, &[], &ctx)?,
+                generate_fields_code(
+                    &data_struct.fields,
+                    Walk::Instance,
+                    &bindings(Walk::Instance),
+                    &ctx,
+                )?,
+                generate_fields_code(
+                    &data_struct.fields,
+                    Walk::Values,
+                    &bindings(Walk::Values),
+                    &ctx,
+                )?,
+                generate_fields_code(
+                    &data_struct.fields,
+                    Walk::ValuesMut,
+                    &bindings(Walk::ValuesMut),
+                    &ctx,
+                )?,
+                generate_fields_count(&data_struct.fields, &ctx)?,
+                generate_fields_have_names(&data_struct.fields, &ctx)?,
+                generate_fields_is_leaf(&data_struct.fields, &ctx)?,
+                // Unsized structs cannot be returned by value
+                match data_struct.fields.iter().last() {
+                    _ if container_attrs.is_unsized() => None,
+                    Some(field) if is_unsized_type(&field.ty) => None,
+                    _ => Some(generate_fields_read_code(
+                        &data_struct.fields,
+                        quote!(Self),
+                        &ctx,
+                    )?),
+                },
+                (
+                    generate_struct_visit_code(&data_struct.fields, Walk::Type, &[], &ctx)?,
+                    generate_struct_visit_code(
+                        &data_struct.fields,
+                        Walk::Values,
+                        &bindings(Walk::Values),
+                        &ctx,
+                    )?,
+                ),
+            )
+        }
+        Data::Enum(data_enum) => {
+            check_field_types(
+                data_enum
+                    .variants
+                    .iter()
+                    .flat_map(|variant| &variant.fields),
+            )?;
+            (
+                generate_enum_code(data_enum, Walk::Type, &ctx)?,
+                generate_enum_code(data_enum, Walk::Instance, &ctx)?,
+                generate_enum_code(data_enum, Walk::Values, &ctx)?,
+                generate_enum_code(data_enum, Walk::ValuesMut, &ctx)?,
+                generate_enum_count(data_enum, &ctx)?,
+                // Every strategy names variants or the tag
+                {
+                    let has_variants = !data_enum.variants.is_empty();
+                    quote!(#has_variants)
+                },
+                quote!(false),
+                Some(generate_enum_read_code(data_enum, &ctx)?),
+                (
+                    generate_enum_visit_code(data_enum, Walk::Type, &ctx)?,
+                    generate_enum_visit_code(data_enum, Walk::Values, &ctx)?,
+                ),
+            )
+        }
+        Data::Union(data_union) => {
+            return Err(syn::Error::new(
+                data_union.union_token.span,
+                "StructReflection can only be used on structs and enums, not unions",
+            ))
+        }
+    };
+
+    let krate = &ctx.krate;
+    // Names the type in the `$ref:Name` entries of recursive types
+    let type_name = struct_name.unraw().to_string();
+
+    // A `static` in a generic function is shared by all its instantiations, so
+    // only types whose names cannot depend on their parameters get their own
+    let names = (input.generics.type_params().next().is_none()
+        && input.generics.const_params().next().is_none())
+    .then(|| {
+        quote! {
+            fn struct_reflection_names() -> ::std::option::Option<&'static [&'static str]>
+            where
+                Self: 'static,
+            {
+                static NAMES: ::std::sync::OnceLock<::std::option::Option<&'static [&'static str]>> =
+                    ::std::sync::OnceLock::new();
+                *NAMES.get_or_init(|| #krate::leak_names(<Self as #krate::StructReflection>::struct_reflection()))
+            }
+        }
+    });
+
+    // Names coming from flattened fields are only known once expanded
+    let check_unique = has_flattened_field(&input.data)?.then(|| {
+        quote! {
+            #krate::check_unique_paths(#type_name, &fields);
+        }
+    });
+    let read = read_code.map(|read_code| {
+        quote! {
+            fn struct_reflection_read(
+                reader: &mut #krate::RowReader<'_>,
+                prefix: #krate::FieldPath,
+            ) -> ::std::option::Option<Self>
+            where
+                Self: ::std::marker::Sized + 'static,
+            {
+                reader.enter(::std::any::type_name::<Self>(), #type_name, prefix, |reader, prefix| {
+                    let _ = (&reader, &prefix);
+                    #read_code
+                })
+            }
+        }
+    });
+    let separator = container_attrs.separator().map(|separator| {
+        quote! {
+            const SEPARATOR: &'static str = #separator;
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics #krate::StructReflection for #struct_name #ty_generics #where_clause {
+            #separator
+
+            const FIELD_COUNT: usize = #field_count;
+
+            const STRUCT_REFLECTION_HAS_NAMES: bool = #has_names;
+
+            const STRUCT_REFLECTION_IS_LEAF: bool = #is_leaf;
+
+            #names
+
+            fn struct_reflection_fields() -> ::std::option::Option<::std::vec::Vec<#krate::FieldInfo>> {
+                #krate::enter_type(::std::any::type_name::<Self>(), #type_name, || {
+                    let mut fields: ::std::vec::Vec<#krate::FieldInfo> = ::std::vec::Vec::new();
+                    #(#field_list_code)*
+                    #check_unique
+                    ::std::option::Option::Some(fields)
+                })
+            }
+
+            fn struct_reflection_instance_fields(&self) -> ::std::option::Option<::std::vec::Vec<#krate::FieldInfo>> {
+                #krate::enter_instance(::std::any::type_name::<Self>(), #type_name, || {
+                    let mut fields: ::std::vec::Vec<#krate::FieldInfo> = ::std::vec::Vec::new();
+                    #(#instance_code)*
+                    #check_unique
+                    ::std::option::Option::Some(fields)
+                })
+            }
+
+            fn struct_reflection_values(&self) -> ::std::option::Option<::std::vec::Vec<#krate::FieldRef<'_>>>
+            where
+                Self: 'static,
+            {
+                let mut fields: ::std::vec::Vec<#krate::FieldRef<'_>> = ::std::vec::Vec::new();
+                #(#values_code)*
+                ::std::option::Option::Some(fields)
+            }
+
+            fn struct_reflection_values_mut(&mut self) -> ::std::option::Option<::std::vec::Vec<#krate::FieldMut<'_>>>
+            where
+                Self: 'static,
+            {
+                let mut fields: ::std::vec::Vec<#krate::FieldMut<'_>> = ::std::vec::Vec::new();
+                #(#values_mut_code)*
+                ::std::option::Option::Some(fields)
+            }
+
+            #read
+
+            fn struct_reflection_walk(walker: &mut #krate::FieldWalker<'_>, key: #krate::FieldKey<'_>) {
+                walker.enter(::std::any::type_name::<Self>(), #type_name, key, |walker| {
+                    #visit_code
+                });
+            }
+
+            fn struct_reflection_walk_value(&self, walker: &mut #krate::FieldWalker<'_>, key: #krate::FieldKey<'_>)
+            where
+                Self: 'static,
+            {
+                #visit_value_code
+            }
+        }
+    })
+}
+
+/// What the generated code walks, and what it pushes into `fields`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Walk {
+    /// The type alone: a `FieldInfo` per entry.
+    Type,
+    /// A value: a `FieldInfo` per entry, with collections and maps expanded
+    /// into their contents.
+    Instance,
+    /// A value: a `FieldRef` per leaf that holds a value.
+    Values,
+    /// A mutably borrowed value: a `FieldMut` per leaf that holds a value.
+    ValuesMut,
+}
+
+impl Walk {
+    /// The type of the entries pushed into `fields`.
+    fn entry(self, ctx: &Context) -> proc_macro2::TokenStream {
+        let krate = &ctx.krate;
+        match self {
+            Walk::Type | Walk::Instance => quote!(#krate::FieldInfo),
+            Walk::Values => quote!(#krate::FieldRef<'_>),
+            Walk::ValuesMut => quote!(#krate::FieldMut<'_>),
+        }
+    }
+
+    /// Whether the walk borrows leaves rather than naming entries.
+    fn borrows_values(self) -> bool {
+        matches!(self, Walk::Values | Walk::ValuesMut)
+    }
+
+    /// `&` or `&mut`, for borrowing parts of the walked value.
+    fn reference(self) -> proc_macro2::TokenStream {
+        match self {
+            Walk::ValuesMut => quote!(&mut),
+            Walk::Type | Walk::Instance | Walk::Values => quote!(&),
+        }
+    }
+
+    /// `iter` or `iter_mut`, for iterating over the walked collections.
+    fn iter(self) -> proc_macro2::TokenStream {
+        match self {
+            Walk::ValuesMut => quote!(iter_mut),
+            Walk::Type | Walk::Instance | Walk::Values => quote!(iter),
+        }
+    }
+}
+
+/// Generates the code pushing the names of `fields`.
+///
+/// Walks of a value take `bindings`, which hold an expression borrowing each
+/// field.
+fn generate_fields_code(
+    fields: &Fields,
+    walk: Walk,
+    bindings: &[proc_macro2::TokenStream],
+    ctx: &Context,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let krate = &ctx.krate;
+    let mut code = Vec::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let field_attrs = FieldAttrs::from_ast(&field.attrs)?;
+        if field_attrs.skip() {
+            continue;
+        }
+
+        // A transparent struct is reflected as its only field's type, a
+        // flattened field's names are inlined into the parent if it has any,
+        // and a prefixed field's entries are nested below its prefix if they
+        // are not a single leaf
+        let prefix = if ctx.container_attrs.transparent() {
+            quote!(#krate::FieldPath::new())
+        } else {
+            let field_segment = ctx.field_segment(i, field, &field_attrs);
+            let options = TypeOptions::new(&field_attrs, ctx);
+            if field_attrs.flatten() {
+                let has_names = generate_type_has_names(&field.ty, options, ctx);
+                quote! {
+                    if #has_names {
+                        #krate::FieldPath::new()
+                    } else {
+                        #krate::FieldPath::from(#field_segment)
+                    }
+                }
+            } else if let Some(nested_prefix) = field_attrs.prefix() {
+                let nested_segment = ctx.named_segment(nested_prefix);
+                let is_leaf = generate_type_is_leaf(&field.ty, options, ctx);
+                quote! {
+                    if #is_leaf {
+                        #krate::FieldPath::from(#field_segment)
+                    } else {
+                        #krate::FieldPath::from(#nested_segment)
+                    }
+                }
+            } else {
+                quote!(#krate::FieldPath::from(#field_segment))
+            }
+        };
+        let binding = bindings.get(i);
+        let field_code = generate_field_code(prefix, field, &field_attrs, walk, binding, ctx)?;
+
+        code.push(match field_attrs.skip_if() {
+            Some(predicate) => quote! {
+                #[cfg(not(#predicate))]
+                #field_code
+            },
+            None => field_code,
+        });
+    }
+
+    Ok(code)
+}
+
+// Each variant's fields are collected into their own `fields` vector, which the
+// chosen strategy then merges into the outer one.
+fn generate_enum_code(
+    data_enum: &syn::DataEnum,
+    walk: Walk,
+    ctx: &Context,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let variant_names = data_enum
+        .variants
+        .iter()
+        .map(|variant| ctx.variant_name(variant))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let krate = &ctx.krate;
+    let entry = walk.entry(ctx);
+
+    if walk == Walk::ValuesMut {
+        return generate_enum_values_mut_code(data_enum, ctx);
+    }
+
+    match ctx.container_attrs.enum_strategy() {
+        // The current variant's entry holds the enum itself
+        EnumStrategy::Variants if walk == Walk::Values => Ok(data_enum
+            .variants
+            .iter()
+            .zip(&variant_names)
+            .map(|(variant, variant_name)| {
+                let variant_ident = &variant.ident;
+                let segment = ctx.named_segment(variant_name);
+                quote! {
+                    if let Self::#variant_ident { .. } = self {
+                        fields.push(#krate::FieldRef {
+                            path: #krate::FieldPath::from(#segment),
+                            value: self,
+                        });
+                    }
+                }
+            })
+            .collect()),
+        EnumStrategy::Variants => Ok(variant_names
+            .iter()
+            .map(|variant_name| {
+                let segment = ctx.named_segment(variant_name);
+                quote! {
+                    fields.push(#krate::FieldInfo {
+                        path: #krate::FieldPath::from(#segment),
+                        type_name: ::std::any::type_name::<Self>(),
+                        kind: #krate::PrimitiveKind::Other,
+                        is_generic: false,
+                    });
+                }
+            })
+            .collect()),
+        EnumStrategy::Prefixed => data_enum
+            .variants
+            .iter()
+            .zip(variant_names)
+            .map(|(variant, variant_name)| {
+                let segment = ctx.named_segment(&variant_name);
+                let variant_code = generate_variant_code(variant, walk, ctx)?;
+                let empty_variant_entry = match walk {
+                    Walk::Type | Walk::Instance => quote! {
+                        #krate::FieldInfo {
+                            path: #krate::FieldPath::from(#segment),
+                            type_name: ::std::any::type_name::<Self>(),
+                            kind: #krate::PrimitiveKind::Other,
+                            is_generic: false,
+                        }
+                    },
+                    Walk::Values | Walk::ValuesMut => quote! {
+                        #krate::FieldRef {
+                            path: #krate::FieldPath::from(#segment),
+                            value: self,
+                        }
+                    },
+                };
+                let code = quote! {
+                    {
+                        let variant_fields = {
+                            let mut fields: ::std::vec::Vec<#entry> = ::std::vec::Vec::new();
+                            #variant_code
+                            fields
+                        };
+                        if variant_fields.is_empty() {
+                            fields.push(#empty_variant_entry);
+                        }
+                        for mut variant_field in variant_fields {
+                            let mut path = #krate::FieldPath::from(#segment);
+                            path.extend(variant_field.path);
+                            variant_field.path = path;
+                            fields.push(variant_field);
+                        }
+                    }
+                };
+
+                // Other variants hold no values
+                Ok(match walk {
+                    Walk::Type | Walk::Instance => code,
+                    Walk::Values | Walk::ValuesMut => {
+                        let variant_ident = &variant.ident;
+                        quote! {
+                            if let Self::#variant_ident { .. } = self {
+                                #code
+                            }
+                        }
+                    }
+                })
+            })
+            .collect(),
+        EnumStrategy::Union => {
+            let tag = ctx.container_attrs.tag();
+            let tag_segment = ctx.named_segment(tag);
+            let tag_code = match walk {
+                Walk::Type | Walk::Instance => quote! {
+                    fields.push(#krate::FieldInfo {
+                        path: #krate::FieldPath::from(#tag_segment),
+                        type_name: ::std::any::type_name::<Self>(),
+                        kind: #krate::PrimitiveKind::String,
+                        is_generic: false,
+                    });
+                },
+                // The tag's value is the current variant's name, as a `&'static str`
+                Walk::Values | Walk::ValuesMut => {
+                    let variant_idents = data_enum.variants.iter().map(|variant| &variant.ident);
+                    quote! {
+                        let tag: &'static &'static str = match self {
+                            #(Self::#variant_idents { .. } => &#variant_names,)*
+                        };
+                        fields.push(#krate::FieldRef {
+                            path: #krate::FieldPath::from(#tag_segment),
+                            value: tag,
+                        });
+                    }
+                }
+            };
+            let mut code = vec![tag_code];
+
+            for variant in &data_enum.variants {
+                for (i, field) in variant.fields.iter().enumerate() {
+                    let field_attrs = FieldAttrs::from_ast(&field.attrs)?;
+                    if !field_attrs.skip() && ctx.field_name(i, field, &field_attrs) == tag {
+                        let message = format!("field name collides with the enum tag `{tag}`");
+                        return Err(match &field.ident {
+                            Some(ident) => syn::Error::new_spanned(ident, message),
+                            None => syn::Error::new_spanned(field, message),
+                        });
+                    }
+                }
+
+                let variant_code = generate_variant_code(variant, walk, ctx)?;
+                code.push(quote! {
+                    {
+                        let variant_fields = {
+                            let mut fields: ::std::vec::Vec<#entry> = ::std::vec::Vec::new();
+                            #variant_code
+                            fields
+                        };
+                        for variant_field in variant_fields {
+                            if !fields.iter().any(|field| field.path == variant_field.path) {
+                                fields.push(variant_field);
+                            }
+                        }
+                    }
+                });
+            }
+
+            Ok(code)
+        }
+    }
+}
+
+/// Generates a constant expression counting the names of `fields`.
+fn generate_fields_count(fields: &Fields, ctx: &Context) -> syn::Result<proc_macro2::TokenStream> {
+    let mut statements = Vec::new();
+
+    for field in fields {
+        let field_attrs = FieldAttrs::from_ast(&field.attrs)?;
+        if field_attrs.skip() {
+            continue;
+        }
+
+        let field_count = generate_type_count(&field.ty, TypeOptions::new(&field_attrs, ctx), ctx);
+        statements.push(match field_attrs.skip_if() {
+            Some(predicate) => quote! {
+                #[cfg(not(#predicate))]
+                {
+                    count += #field_count;
+                }
+            },
+            None => quote! {
+                count += #field_count;
+            },
+        });
+    }
+
+    Ok(quote! {
+        {
+            let mut count: usize = 0;
+            #(#statements)*
+            count
+        }
+    })
+}
+
+fn generate_enum_count(
+    data_enum: &syn::DataEnum,
+    ctx: &Context,
+) -> syn::Result<proc_macro2::TokenStream> {
+    match ctx.container_attrs.enum_strategy() {
+        EnumStrategy::Variants => {
+            let count = data_enum.variants.len();
+            Ok(quote!(#count))
+        }
+        // A variant without names of its own is reflected as its bare name
+        EnumStrategy::Prefixed => {
+            let variant_counts = data_enum
+                .variants
+                .iter()
+                .map(|variant| {
+                    let count = generate_fields_count(&variant.fields, ctx)?;
+                    Ok(quote! {
+                        match #count {
+                            0 => 1,
+                            count => count,
+                        }
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            Ok(quote!(0 #(+ #variant_counts)*))
+        }
+        // Variant fields sharing a name are counted once, which is only known
+        // to be right when they also share a type
+        EnumStrategy::Union => {
+            let mut seen: Vec<(String, String)> = Vec::new();
+            let mut statements = Vec::new();
+
+            for variant in &data_enum.variants {
+                for (i, field) in variant.fields.iter().enumerate() {
+                    let field_attrs = FieldAttrs::from_ast(&field.attrs)?;
+                    if field_attrs.skip() {
+                        continue;
+                    }
+                    if field_attrs.flatten()
+                        || field_attrs.prefix().is_some()
+                        || field_attrs.skip_if().is_some()
+                    {
+                        return Ok(union_count_unavailable());
+                    }
+
+                    let name = ctx.field_name(i, field, &field_attrs);
+                    let ty = &field.ty;
+                    let attrs = field
+                        .attrs
+                        .iter()
+                        .filter(|attr| attr.path().is_ident("reflect"));
+                    let signature = quote!(#(#attrs)* #ty).to_string();
+                    if let Some((_, seen_signature)) = seen.iter().find(|(seen, _)| *seen == name) {
+                        if *seen_signature != signature {
+                            return Ok(union_count_unavailable());
+                        }
+                        continue;
+                    }
+
+                    let options = TypeOptions::new(&field_attrs, ctx);
+                    statements.push(generate_type_count(&field.ty, options, ctx));
+                    seen.push((name, signature));
+                }
+            }
+
+            Ok(quote!(1 #(+ #statements)*))
+        }
+    }
+}
+
+// Raised when `FIELD_COUNT` is used, not when the enum is derived
+fn union_count_unavailable() -> proc_macro2::TokenStream {
+    quote! {
+        ::std::panic!("FIELD_COUNT is not available for `union` enums whose variants have flattened, prefixed or conditionally skipped fields, or same-named fields of different types")
+    }
+}
+
+/// Generates a constant expression telling whether any name of `fields` has a
+/// named segment, for `STRUCT_REFLECTION_HAS_NAMES`.
+fn generate_fields_have_names(
+    fields: &Fields,
+    ctx: &Context,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut statements = Vec::new();
+
+    for field in fields {
+        let field_attrs = FieldAttrs::from_ast(&field.attrs)?;
+        if field_attrs.skip() {
+            continue;
+        }
+
+        // A named field names its entries, even flattened, as it keeps its
+        // segment when its type has no names of its own
+        let is_named = !ctx.container_attrs.transparent()
+            && (field.ident.is_some() || field_attrs.rename().is_some());
+        let options = TypeOptions::new(&field_attrs, ctx);
+        let type_has_names = generate_type_has_names(&field.ty, options, ctx);
+        let field_has_names = if is_named {
+            quote!(true)
+        } else if field_attrs.prefix().is_some() {
+            let is_leaf = generate_type_is_leaf(&field.ty, options, ctx);
+            quote!((!#is_leaf || #type_has_names))
+        } else {
+            type_has_names
+        };
+        statements.push(match field_attrs.skip_if() {
+            Some(predicate) => quote! {
+                #[cfg(not(#predicate))]
+                {
+                    has_names = has_names || #field_has_names;
+                }
+            },
+            None => quote! {
+                has_names = has_names || #field_has_names;
+            },
+        });
+    }
+
+    Ok(quote! {
+        {
+            let mut has_names = false;
+            #(#statements)*
+            has_names
+        }
+    })
+}
+
+/// Generates a constant expression telling whether any name for a value of
+/// type `ty` has a named segment, following the same rules as
+/// [`generate_type_code`].
+fn generate_type_has_names(
+    ty: &syn::Type,
+    options: TypeOptions,
+    ctx: &Context,
+) -> proc_macro2::TokenStream {
+    let ty = ungroup(ty);
+    let krate = &ctx.krate;
+
+    if ctx.is_generic_type(ty) {
+        return quote!(false);
+    }
+
+    if let Some(inner_type) = option_inner_type(ty) {
+        return match options.option_style {
+            OptionStyle::Collapse => quote!(false),
+            OptionStyle::Suffix | OptionStyle::Prefix => {
+                generate_type_has_names(inner_type, options, ctx)
+            }
+        };
+    }
+
+    if let Some(element_type) = element_type(ty).filter(|_| options.elements) {
+        return generate_type_has_names(element_type, options, ctx);
+    }
+
+    if let Some(value_type) = map_value_type(ty).filter(|_| options.values) {
+        return generate_type_has_names(value_type, options, ctx);
+    }
+
+    match ty {
+        syn::Type::Tuple(tuple_type) => {
+            let element_names = tuple_type
+                .elems
+                .iter()
+                .map(|elem| generate_type_has_names(elem, options, ctx));
+            quote!((false #(|| #element_names)*))
+        }
+        syn::Type::Array(array_type) => generate_type_has_names(&array_type.elem, options, ctx),
+        _ if options.leaf => quote!(false),
+        _ => {
+            let helper_type = boxed_type(ty).unwrap_or(ty);
+            quote!(#krate::has_names::<#helper_type>())
+        }
+    }
+}
+
+/// Generates a constant expression telling whether a struct with `fields` is
+/// a single leaf, for `STRUCT_REFLECTION_IS_LEAF`: only a transparent struct
+/// around a leaf is.
+fn generate_fields_is_leaf(
+    fields: &Fields,
+    ctx: &Context,
+) -> syn::Result<proc_macro2::TokenStream> {
+    if !ctx.container_attrs.transparent() {
+        return Ok(quote!(false));
+    }
+
+    for field in fields {
+        let field_attrs = FieldAttrs::from_ast(&field.attrs)?;
+        if !field_attrs.skip() {
+            return Ok(generate_type_is_leaf(
+                &field.ty,
+                TypeOptions::new(&field_attrs, ctx),
+                ctx,
+            ));
+        }
+    }
+    Ok(quote!(false))
+}
+
+/// Generates a constant expression telling whether a value of type `ty` is a
+/// single leaf, with no entries nested below it, following the same rules as
+/// [`generate_type_code`].
+fn generate_type_is_leaf(
+    ty: &syn::Type,
+    options: TypeOptions,
+    ctx: &Context,
+) -> proc_macro2::TokenStream {
+    let ty = ungroup(ty);
+    let krate = &ctx.krate;
+
+    if ctx.is_generic_type(ty) {
+        return quote!(true);
+    }
+
+    if let Some(inner_type) = option_inner_type(ty) {
+        return match options.option_style {
+            OptionStyle::Collapse => quote!(true),
+            OptionStyle::Suffix | OptionStyle::Prefix => {
+                generate_type_is_leaf(inner_type, options, ctx)
+            }
+        };
+    }
+
+    if (element_type(ty).is_some() && options.elements)
+        || (map_value_type(ty).is_some() && options.values)
+    {
+        return quote!(false);
+    }
+
+    match ty {
+        syn::Type::Tuple(_) | syn::Type::Array(_) => quote!(false),
+        _ if options.leaf => quote!(true),
+        _ => {
+            let helper_type = boxed_type(ty).unwrap_or(ty);
+            quote!(#krate::is_leaf::<#helper_type>())
+        }
+    }
+}
+
+/// Generates a constant expression counting the names for a value of type `ty`,
+/// following the same rules as [`generate_type_code`].
+fn generate_type_count(
+    ty: &syn::Type,
+    options: TypeOptions,
+    ctx: &Context,
+) -> proc_macro2::TokenStream {
+    let ty = ungroup(ty);
+    let krate = &ctx.krate;
+
+    if ctx.is_generic_type(ty) {
+        return quote!(1);
+    }
+
+    if let Some(inner_type) = option_inner_type(ty) {
+        return match options.option_style {
+            OptionStyle::Collapse => quote!(1),
+            OptionStyle::Suffix | OptionStyle::Prefix => {
+                generate_type_count(inner_type, options, ctx)
+            }
+        };
+    }
+
+    if let Some(element_type) = element_type(ty).filter(|_| options.elements) {
+        return generate_type_count(element_type, options, ctx);
+    }
+
+    if let Some(value_type) = map_value_type(ty).filter(|_| options.values) {
+        return generate_type_count(value_type, options, ctx);
+    }
+
+    match ty {
+        syn::Type::Tuple(tuple_type) => {
+            let element_counts = tuple_type
+                .elems
+                .iter()
+                .map(|elem| generate_type_count(elem, options, ctx));
+            quote!((0 #(+ #element_counts)*))
+        }
+        syn::Type::Array(array_type) => {
+            let array_len = &array_type.len;
+            let elem_count = generate_type_count(&array_type.elem, options, ctx);
+            quote!(((#array_len) * #elem_count))
+        }
+        _ if options.leaf => quote!(1),
+        _ => {
+            let helper_type = boxed_type(ty).unwrap_or(ty);
+            quote!(#krate::field_count::<#helper_type>())
+        }
+    }
+}
+
+/// Generates the code pushing the names of a variant's fields. For walks of a
+/// value, the fields of the variant `self` holds are walked by value.
+fn generate_variant_code(
+    variant: &syn::Variant,
+    walk: Walk,
+    ctx: &Context,
+) -> syn::Result<proc_macro2::TokenStream> {
+    // Other variants are walked by type, and hold no values
+    let type_code = if walk.borrows_values() {
+        Vec::new()
+    } else {
+        generate_fields_code(&variant.fields, Walk::Type, &[], ctx)?
+    };
+    if walk == Walk::Type {
+        return Ok(quote!(#(#type_code)*));
+    }
+
+    let (pattern, bindings) = variant_pattern(variant);
+    let value_code = generate_fields_code(&variant.fields, walk, &bindings, ctx)?;
+
+    Ok(quote! {
+        match self {
+            #[allow(unused_variables)]
+            #pattern => {
+                #(#value_code)*
+            }
+            #[allow(unreachable_patterns)]
+            _ => {
+                #(#type_code)*
+            }
+        }
+    })
+}
+
+/// A pattern matching `variant` and binding its fields to the returned names.
+fn variant_pattern(
+    variant: &syn::Variant,
+) -> (proc_macro2::TokenStream, Vec<proc_macro2::TokenStream>) {
+    let bindings: Vec<_> = (0..variant.fields.len())
+        .map(|i| {
+            let binding = quote::format_ident!("field_{}", i);
+            quote!(#binding)
+        })
+        .collect();
+
+    let variant_ident = &variant.ident;
+    let pattern = match &variant.fields {
+        Fields::Named(fields) => {
+            let idents = fields.named.iter().map(|field| &field.ident);
+            quote!(Self::#variant_ident { #(#idents: #bindings),* })
+        }
+        Fields::Unnamed(_) => quote!(Self::#variant_ident(#(#bindings),*)),
+        Fields::Unit => quote!(Self::#variant_ident),
+    };
+
+    (pattern, bindings)
+}
+
+/// Generates the code pushing a `FieldMut` per leaf of the variant `self`
+/// holds. Every field is borrowed by a single `match`, as `self` cannot be
+/// matched again while they are.
+fn generate_enum_values_mut_code(
+    data_enum: &syn::DataEnum,
+    ctx: &Context,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let krate = &ctx.krate;
+    let mut arms = Vec::new();
+
+    for variant in &data_enum.variants {
+        let (pattern, bindings) = variant_pattern(variant);
+        let value_code = generate_fields_code(&variant.fields, Walk::ValuesMut, &bindings, ctx)?;
+        let arm_code = match ctx.container_attrs.enum_strategy() {
+            // The entries name whole variants, which cannot be assigned
+            EnumStrategy::Variants => quote!(),
+            EnumStrategy::Prefixed => {
+                let segment = ctx.named_segment(&ctx.variant_name(variant)?);
+                quote! {
+                    let mut variant_fields: ::std::vec::Vec<#krate::FieldMut<'_>> = ::std::vec::Vec::new();
+                    {
+                        let fields = &mut variant_fields;
+                        #(#value_code)*
+                    }
+                    for mut variant_field in variant_fields {
+                        let mut path = #krate::FieldPath::from(#segment);
+                        path.extend(variant_field.path);
+                        variant_field.path = path;
+                        fields.push(variant_field);
+                    }
+                }
+            }
+            EnumStrategy::Union => quote!(#(#value_code)*),
+        };
+        arms.push(quote! {
+            #[allow(unused_variables)]
+            #pattern => {
+                #arm_code
+            }
+        });
+    }
+
+    Ok(vec![quote! {
+        match self {
+            #(#arms)*
+        }
+    }])
+}
+
+/// Generates an expression reading `fields` from the `reader` in scope, below
+/// `prefix`, into an `Option` of the value built by `constructor`.
+///
+/// Every field is read before any is found missing, so that all the columns at
+/// fault are recorded. Skipped fields take their type's `Default` value.
+fn generate_fields_read_code(
+    fields: &Fields,
+    constructor: proc_macro2::TokenStream,
+    ctx: &Context,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let krate = &ctx.krate;
+    let mut statements = Vec::new();
+    let mut members = Vec::new();
+    let mut bindings = Vec::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let field_attrs = FieldAttrs::from_ast(&field.attrs)?;
+        let binding = quote::format_ident!("field_{}", i);
+        let ty = &field.ty;
+
+        let path = if ctx.container_attrs.transparent() {
+            quote!(prefix.clone())
+        } else {
+            let field_segment = ctx.field_segment(i, field, &field_attrs);
+            let options = TypeOptions::new(&field_attrs, ctx);
+            if field_attrs.flatten() {
+                let has_names = generate_type_has_names(ty, options, ctx);
+                quote! {
+                    if #has_names {
+                        prefix.clone()
+                    } else {
+                        prefix.child(#field_segment)
+                    }
+                }
+            } else if let Some(nested_prefix) = field_attrs.prefix() {
+                let nested_segment = ctx.named_segment(nested_prefix);
+                let is_leaf = generate_type_is_leaf(ty, options, ctx);
+                quote! {
+                    if #is_leaf {
+                        prefix.child(#field_segment)
+                    } else {
+                        prefix.child(#nested_segment)
+                    }
+                }
+            } else {
+                quote!(prefix.child(#field_segment))
+            }
+        };
+        let default_code = quote! {
+            {
+                use #krate::{SkippedDefault as _, SkippedNoDefault as _};
+                reader.skipped(#path, (&#krate::SkippedField::<#ty>::new()).default_value())
+            }
+        };
+
+        let statement = if field_attrs.skip() {
+            quote!(let #binding = #default_code;)
+        } else {
+            let options = TypeOptions::new(&field_attrs, ctx);
+            let read_code = generate_read_code(ty, options, ctx);
+            let read_code = quote! {
+                {
+                    let prefix = #path;
+                    #read_code
+                }
+            };
+
+            match field_attrs.skip_if() {
+                Some(predicate) => quote! {
+                    #[cfg(not(#predicate))]
+                    let #binding = #read_code;
+                    #[cfg(#predicate)]
+                    let #binding = #default_code;
+                },
+                None => quote!(let #binding = #read_code;),
+            }
+        };
+        statements.push(statement);
+
+        members.push(match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = syn::Index::from(i);
+                quote!(#index)
+            }
+        });
+        bindings.push(binding);
+    }
+
+    Ok(quote! {
+        {
+            #(#statements)*
+            #[allow(unreachable_patterns)]
+            match (#(#bindings,)*) {
+                (#(::std::option::Option::Some(#bindings),)*) => {
+                    ::std::option::Option::Some(#constructor { #(#members: #bindings),* })
+                }
+                _ => ::std::option::Option::None,
+            }
+        }
+    })
+}
+
+/// Generates an expression reading an enum: the variant named by the tag for
+/// the `union` strategy, and the first variant with a value in any of its
+/// columns otherwise.
+fn generate_enum_read_code(
+    data_enum: &syn::DataEnum,
+    ctx: &Context,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let variant_names = data_enum
+        .variants
+        .iter()
+        .map(|variant| ctx.variant_name(variant))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    if let EnumStrategy::Union = ctx.container_attrs.enum_strategy() {
+        let tag_segment = ctx.named_segment(ctx.container_attrs.tag());
+        let arms = data_enum
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(i, variant)| {
+                let variant_ident = &variant.ident;
+                let fields_code =
+                    generate_fields_read_code(&variant.fields, quote!(Self::#variant_ident), ctx)?;
+                Ok(quote! {
+                    ::std::option::Option::Some(#i) => #fields_code,
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        return Ok(quote! {
+            match reader.tag(
+                prefix.child(#tag_segment),
+                &[#(#variant_names),*],
+                ::std::any::type_name::<Self>(),
+            ) {
+                #(#arms)*
+                _ => ::std::option::Option::None,
+            }
+        });
+    }
+
+    let mut variant_reads = Vec::new();
+    for (variant, variant_name) in data_enum.variants.iter().zip(&variant_names) {
+        let variant_ident = &variant.ident;
+        let segment = ctx.named_segment(variant_name);
+        let fields_code =
+            generate_fields_read_code(&variant.fields, quote!(Self::#variant_ident), ctx)?;
+        let mut reflected_fields = 0;
+        for field in &variant.fields {
+            if !FieldAttrs::from_ast(&field.attrs)?.skip() {
+                reflected_fields += 1;
+            }
+        }
+
+        let variant_code = match ctx.container_attrs.enum_strategy() {
+            // The entries name whole variants, which can only be told apart
+            // when they hold nothing else
+            EnumStrategy::Variants if reflected_fields > 0 => quote! {
+                reader.unsupported(prefix, ::std::any::type_name::<Self>())
+            },
+            _ if reflected_fields == 0 => quote! {
+                if reader.marker(prefix.clone()) {
+                    #fields_code
+                } else {
+                    ::std::option::Option::None
+                }
+            },
+            _ => fields_code,
+        };
+        variant_reads.push(quote! {
+            {
+                let checkpoint = reader.begin_option();
+                let value = {
+                    let prefix = prefix.child(#segment);
+                    #variant_code
+                };
+                match reader.end_option(checkpoint, value) {
+                    ::std::option::Option::Some(::std::option::Option::None) => {}
+                    value => {
+                        if variant.is_none() {
+                            variant = ::std::option::Option::Some(value.flatten());
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
+        {
+            let mut variant: ::std::option::Option<::std::option::Option<Self>> = ::std::option::Option::None;
+            #(#variant_reads)*
+            match variant {
+                ::std::option::Option::Some(value) => value,
+                ::std::option::Option::None => reader.no_variant(prefix, ::std::any::type_name::<Self>()),
+            }
+        }
+    })
+}
+
+/// Generates an expression reading a value of type `ty` from the `reader` in
+/// scope, at `prefix`, following the same rules as [`generate_type_code`].
+fn generate_read_code(
+    ty: &syn::Type,
+    options: TypeOptions,
+    ctx: &Context,
+) -> proc_macro2::TokenStream {
+    let ty = ungroup(ty);
+    let krate = &ctx.krate;
+
+    if ctx.is_generic_type(ty) {
+        return quote!(reader.leaf::<#ty>(prefix));
+    }
+
+    if let Some(inner_type) = option_inner_type(ty) {
+        let inner_code = generate_read_code(inner_type, options, ctx);
+        let read_code = match options.option_style {
+            OptionStyle::Collapse => {
+                return quote! {
+                    reader.optional_leaf::<#inner_type>(prefix.child(#krate::Segment::OptionalMarker))
+                };
+            }
+            OptionStyle::Suffix => quote!(reader.suffixed(|reader| #inner_code)),
+            OptionStyle::Prefix => quote! {
+                {
+                    let prefix = prefix.child(#krate::Segment::OptionalMarker);
+                    #inner_code
+                }
+            },
+        };
+        return quote! {
+            {
+                let checkpoint = reader.begin_option();
+                let value = #read_code;
+                reader.end_option(checkpoint, value)
+            }
+        };
+    }
+
+    // The contents of collections and maps are not columns of the row, which
+    // only hold the nulls standing for them
+    if (element_type(ty).is_some() && options.elements)
+        || (map_value_type(ty).is_some() && options.values)
+    {
+        let type_code = generate_type_code(ty, options, ctx);
+        return quote! {
+            {
+                let mut fields: ::std::vec::Vec<#krate::FieldInfo> = ::std::vec::Vec::new();
+                {
+                    let prefix = prefix.clone();
+                    #type_code
+                }
+                reader.empty::<#ty>(
+                    fields.into_iter().map(|field| field.path),
+                    ::std::any::type_name::<#ty>(),
+                )
+            }
+        };
+    }
+
+    match ty {
+        syn::Type::Tuple(tuple_type) if tuple_type.elems.is_empty() => quote! {
+            ::std::option::Option::Some(())
+        },
+        syn::Type::Tuple(tuple_type) => {
+            let elements: Vec<_> = (0..tuple_type.elems.len())
+                .map(|i| quote::format_ident!("element_{}", i))
+                .collect();
+            let element_code = tuple_type.elems.iter().enumerate().map(|(i, elem)| {
+                let elem_code = generate_read_code(elem, options, ctx);
+                quote! {
+                    {
+                        let prefix = prefix.child(#krate::Segment::TupleIndex(#i));
+                        #elem_code
+                    }
+                }
+            });
+            quote! {
+                {
+                    #(let #elements = #element_code;)*
+                    match (#(#elements,)*) {
+                        (#(::std::option::Option::Some(#elements),)*) => {
+                            ::std::option::Option::Some((#(#elements,)*))
+                        }
+                        _ => ::std::option::Option::None,
+                    }
+                }
+            }
+        }
+        syn::Type::Array(array_type) => {
+            let array_len = &array_type.len;
+            let elem = &array_type.elem;
+            let elem_code = generate_read_code(elem, options, ctx);
+            quote! {
+                {
+                    let elements: ::std::vec::Vec<::std::option::Option<#elem>> = (0..(#array_len))
+                        .map(|i| {
+                            let prefix = prefix.child(#krate::Segment::ArrayIndex(i));
+                            #elem_code
+                        })
+                        .collect();
+                    elements
+                        .into_iter()
+                        .collect::<::std::option::Option<::std::vec::Vec<#elem>>>()
+                        .and_then(|elements| <[#elem; #array_len]>::try_from(elements).ok())
+                }
+            }
+        }
+        _ if options.leaf => quote!(reader.leaf::<#ty>(prefix)),
+        _ => match boxed_type(ty) {
+            Some(inner_type) => quote! {
+                <#inner_type as #krate::StructReflectionHelper>::read_boxed(reader, prefix)
+            },
+            None => quote!(<#ty as #krate::StructReflectionHelper>::read_row(reader, prefix)),
+        },
+    }
+}
+
+/// Generates the code visiting a struct as the node at the `key` in scope, with
+/// the `walker` in scope. Walks of a value take `bindings`, as for
+/// [`generate_fields_code`].
+fn generate_struct_visit_code(
+    fields: &Fields,
+    walk: Walk,
+    bindings: &[proc_macro2::TokenStream],
+    ctx: &Context,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let fields_code = generate_fields_visit_code(fields, walk, bindings, &mut Vec::new(), ctx)?;
+
+    // A transparent struct is visited as its only field
+    if ctx.container_attrs.transparent() {
+        return Ok(quote!(#(#fields_code)*));
+    }
+    Ok(quote! {
+        walker.visit_struct(key, ::std::any::type_name::<Self>(), |walker| {
+            #(#fields_code)*
+        });
+    })
+}
+
+/// Generates the code visiting each of `fields` as the node keyed by its name.
+///
+/// Fields named in `visited` are left out, and the names of the others added
+/// to it, so that the fields shared by the variants of a `union` enum are
+/// visited once, as they are named once.
+fn generate_fields_visit_code(
+    fields: &Fields,
+    walk: Walk,
+    bindings: &[proc_macro2::TokenStream],
+    visited: &mut Vec<String>,
+    ctx: &Context,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let mut code = Vec::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let field_attrs = FieldAttrs::from_ast(&field.attrs)?;
+        if field_attrs.skip() {
+            continue;
+        }
+
+        // The only field of a transparent struct takes the struct's key
+        let key = if ctx.container_attrs.transparent() {
+            quote!()
+        } else {
+            if !field_attrs.flatten() {
+                let name = ctx.field_name(i, field, &field_attrs);
+                if visited.contains(&name) {
+                    continue;
+                }
+                visited.push(name);
+            }
+            let field_key = ctx.field_key(i, field, &field_attrs);
+            match field_attrs.prefix() {
+                // As in the names, the prefix only stands for the field when
+                // it is not a single leaf
+                Some(nested_prefix) => {
+                    let krate = &ctx.krate;
+                    let is_leaf =
+                        generate_type_is_leaf(&field.ty, TypeOptions::new(&field_attrs, ctx), ctx);
+                    quote! {
+                        let key = if #is_leaf {
+                            #field_key
+                        } else {
+                            #krate::FieldKey::Named(#nested_prefix)
+                        };
+                    }
+                }
+                None => quote!(let key = #field_key;),
+            }
+        };
+        let value = bindings.get(i).map(|binding| quote!(let value = #binding;));
+        let visit_code =
+            generate_visit_code(&field.ty, TypeOptions::new(&field_attrs, ctx), walk, ctx);
+        let mut field_code = quote! {
+            {
+                #key
+                #value
+                #visit_code
+            }
+        };
+        if field_attrs.flatten() {
+            let has_names =
+                generate_type_has_names(&field.ty, TypeOptions::new(&field_attrs, ctx), ctx);
+            field_code = quote! {
+                if #has_names {
+                    walker.flattened(|walker| #field_code);
+                } else {
+                    #field_code
+                }
+            };
+        }
+
+        code.push(match field_attrs.skip_if() {
+            Some(predicate) => quote! {
+                #[cfg(not(#predicate))]
+                #field_code
+            },
+            None => field_code,
+        });
+    }
+
+    Ok(code)
+}
+
+/// Generates the code visiting an enum as the node at the `key` in scope, with
+/// the entries of its strategy. Walks of a value only visit the current
+/// variant.
+fn generate_enum_visit_code(
+    data_enum: &syn::DataEnum,
+    walk: Walk,
+    ctx: &Context,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let krate = &ctx.krate;
+    let by_value = walk.borrows_values();
+    let leaf_value = if by_value {
+        quote!(::std::option::Option::Some(self))
+    } else {
+        quote!(::std::option::Option::None)
+    };
+    // The entry naming a whole variant holds the enum itself
+    let variant_leaf = |variant_name: &str| {
+        quote! {
+            walker.leaf(#krate::FieldKey::Named(#variant_name), #krate::Leaf {
+                type_name: ::std::any::type_name::<Self>(),
+                kind: #krate::PrimitiveKind::Other,
+                is_generic: false,
+                value: #leaf_value,
+            });
+        }
+    };
+
+    let mut code = Vec::new();
+    let mut arms = Vec::new();
+    let mut visited = Vec::new();
+
+    if let EnumStrategy::Union = ctx.container_attrs.enum_strategy() {
+        let tag = ctx.container_attrs.tag();
+        // The tag's value is the current variant's name, as a `&'static str`
+        let tag_value = if by_value {
+            let variant_idents = data_enum.variants.iter().map(|variant| &variant.ident);
+            let variant_names = data_enum
+                .variants
+                .iter()
+                .map(|variant| ctx.variant_name(variant))
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! {
+                ::std::option::Option::Some(match self {
+                    #(Self::#variant_idents { .. } => &#variant_names,)*
+                } as &'static &'static str)
+            }
+        } else {
+            quote!(::std::option::Option::None)
+        };
+        code.push(quote! {
+            walker.leaf(#krate::FieldKey::Named(#tag), #krate::Leaf {
+                type_name: ::std::any::type_name::<Self>(),
+                kind: #krate::PrimitiveKind::String,
+                is_generic: false,
+                value: #tag_value,
+            });
+        });
+    }
+
+    for variant in &data_enum.variants {
+        let variant_name = ctx.variant_name(variant)?;
+        let (pattern, bindings) = variant_pattern(variant);
+        let bindings = if by_value { bindings } else { Vec::new() };
+
+        let variant_code = match ctx.container_attrs.enum_strategy() {
+            EnumStrategy::Variants => variant_leaf(&variant_name),
+            EnumStrategy::Prefixed => {
+                let mut reflected_fields = 0;
+                for field in &variant.fields {
+                    if !FieldAttrs::from_ast(&field.attrs)?.skip() {
+                        reflected_fields += 1;
+                    }
+                }
+
+                if reflected_fields == 0 {
+                    variant_leaf(&variant_name)
+                } else {
+                    let fields_code = generate_fields_visit_code(
+                        &variant.fields,
+                        walk,
+                        &bindings,
+                        &mut Vec::new(),
+                        ctx,
+                    )?;
+                    quote! {
+                        walker.visit_struct(
+                            #krate::FieldKey::Named(#variant_name),
+                            ::std::any::type_name::<Self>(),
+                            |walker| {
+                                #(#fields_code)*
+                            },
+                        );
+                    }
+                }
+            }
+            EnumStrategy::Union => {
+                // Walks of a value only visit the current variant's fields
+                if by_value {
+                    visited.clear();
+                }
+                let fields_code = generate_fields_visit_code(
+                    &variant.fields,
+                    walk,
+                    &bindings,
+                    &mut visited,
+                    ctx,
+                )?;
+                quote!(#(#fields_code)*)
+            }
+        };
+
+        if by_value {
+            arms.push(quote! {
+                #[allow(unused_variables)]
+                #pattern => {
+                    #variant_code
+                }
+            });
+        } else {
+            code.push(variant_code);
+        }
+    }
+
+    if by_value {
+        code.push(quote! {
+            match self {
+                #(#arms)*
+            }
+        });
+    }
+
+    Ok(quote! {
+        walker.visit_struct(key, ::std::any::type_name::<Self>(), |walker| {
+            #(#code)*
+        });
+    })
+}
+
+/// Generates the code visiting a value of type `ty` as the node at the `key`
+/// in scope, following the same rules as [`generate_type_code`] and, in walks
+/// of a value, as [`generate_value_code`] for the `value` in scope.
+fn generate_visit_code(
+    ty: &syn::Type,
+    options: TypeOptions,
+    walk: Walk,
+    ctx: &Context,
+) -> proc_macro2::TokenStream {
+    let ty = ungroup(ty);
+    let krate = &ctx.krate;
+    let by_value = walk.borrows_values();
+    let leaf_value = if by_value {
+        quote!(::std::option::Option::Some(value))
+    } else {
+        quote!(::std::option::Option::None)
+    };
+
+    // Generic parameters are only known once instantiated
+    if ctx.is_generic_type(ty) {
+        return quote! {
+            walker.leaf(key, #krate::Leaf {
+                type_name: ::std::any::type_name::<#ty>(),
+                kind: #krate::PrimitiveKind::from_type_name(::std::any::type_name::<#ty>()),
+                is_generic: true,
+                value: #leaf_value,
+            });
+        };
+    }
+
+    if let Some(inner_type) = option_inner_type(ty) {
+        let inner_code = match options.option_style {
+            // The whole option is the leaf, left out when it holds `None` and
+            // holding the option's value, whose type it reports, otherwise
+            OptionStyle::Collapse => {
+                let is_generic = ctx.is_generic_type(inner_type);
+                let leaf_code = quote! {
+                    walker.leaf(#krate::FieldKey::Optional, #krate::Leaf {
+                        type_name: ::std::any::type_name::<#inner_type>(),
+                        kind: #krate::PrimitiveKind::from_type_name(::std::any::type_name::<#inner_type>()),
+                        is_generic: #is_generic,
+                        value: #leaf_value,
+                    });
+                };
+                if by_value {
+                    quote! {
+                        if let ::std::option::Option::Some(value) = value {
+                            #leaf_code
+                        }
+                    }
+                } else {
+                    leaf_code
+                }
+            }
+            OptionStyle::Suffix | OptionStyle::Prefix => {
+                let inner_code = generate_visit_code(inner_type, options, walk, ctx);
+                if by_value {
+                    quote! {
+                        if let ::std::option::Option::Some(value) = value {
+                            let key = #krate::FieldKey::Optional;
+                            #inner_code
+                        }
+                    }
+                } else {
+                    quote! {
+                        let key = #krate::FieldKey::Optional;
+                        #inner_code
+                    }
+                }
+            }
+        };
+        let style = match options.option_style {
+            OptionStyle::Suffix => quote!(#krate::OptionStyle::Suffix),
+            OptionStyle::Prefix => quote!(#krate::OptionStyle::Prefix),
+            OptionStyle::Collapse => quote!(#krate::OptionStyle::Collapse),
+        };
+        return quote! {
+            walker.visit_option(key, #style, |walker| {
+                #inner_code
+            });
+        };
+    }
+
+    if let Some(element_type) = element_type(ty).filter(|_| options.elements) {
+        let element_code = generate_visit_code(element_type, options, walk, ctx);
+        return if by_value {
+            quote! {
+                walker.visit_array(key, ::std::option::Option::Some(value.len()), |walker| {
+                    for (i, value) in value.iter().enumerate() {
+                        let key = #krate::FieldKey::ArrayIndex(i);
+                        #element_code
+                    }
+                });
+            }
+        } else {
+            quote! {
+                walker.visit_array(key, ::std::option::Option::None, |walker| {
+                    let key = #krate::FieldKey::Element;
+                    #element_code
+                });
+            }
+        };
+    }
+
+    if let Some(value_type) = map_value_type(ty).filter(|_| options.values) {
+        let value_code = generate_visit_code(value_type, options, walk, ctx);
+        return if by_value {
+            quote! {
+                walker.visit_map(key, ::std::option::Option::Some(value.len()), |walker| {
+                    for (map_key, value) in value.iter() {
+                        let map_key = ::std::string::ToString::to_string(map_key);
+                        let key = #krate::FieldKey::Key(&map_key);
+                        #value_code
+                    }
+                });
+            }
+        } else {
+            quote! {
+                walker.visit_map(key, ::std::option::Option::None, |walker| {
+                    let key = #krate::FieldKey::AnyKey;
+                    #value_code
+                });
+            }
+        };
+    }
+
+    match ty {
+        syn::Type::Tuple(tuple_type) if tuple_type.elems.is_empty() => {
+            let value = by_value.then(|| quote!(let _ = value;));
+            quote! {
+                let _ = key;
+                #value
+            }
+        }
+        syn::Type::Tuple(tuple_type) => {
+            let len = tuple_type.elems.len();
+            let element_code = tuple_type.elems.iter().enumerate().map(|(i, elem)| {
+                let index = syn::Index::from(i);
+                let value = by_value.then(|| quote!(let value = &value.#index;));
+                let elem_code = generate_visit_code(elem, options, walk, ctx);
+                quote! {
+                    {
+                        let key = #krate::FieldKey::TupleIndex(#i);
+                        #value
+                        #elem_code
+                    }
+                }
+            });
+            quote! {
+                walker.visit_tuple(key, #len, |walker| {
+                    #(#element_code)*
+                });
+            }
+        }
+        syn::Type::Array(array_type) => {
+            let array_len = &array_type.len;
+            let elem_code = generate_visit_code(&array_type.elem, options, walk, ctx);
+            if by_value {
+                quote! {
+                    walker.visit_array(key, ::std::option::Option::Some(#array_len), |walker| {
+                        for (i, value) in value.iter().enumerate() {
+                            let key = #krate::FieldKey::ArrayIndex(i);
+                            #elem_code
+                        }
+                    });
+                }
+            } else {
+                quote! {
+                    walker.visit_array(key, ::std::option::Option::Some(#array_len), |walker| {
+                        for i in 0..(#array_len) {
+                            let key = #krate::FieldKey::ArrayIndex(i);
+                            #elem_code
+                        }
+                    });
+                }
+            }
+        }
+        _ if options.leaf => quote! {
+            walker.leaf(key, #krate::Leaf {
+                type_name: ::std::any::type_name::<#ty>(),
+                kind: #krate::PrimitiveKind::Other,
+                is_generic: false,
+                value: #leaf_value,
+            });
+        },
+        _ => match (boxed_type(ty), by_value) {
+            (Some(inner_type), true) => quote! {
+                <#inner_type as #krate::StructReflectionHelper>::walk_boxed_value(value, walker, key);
+            },
+            (Some(inner_type), false) => quote! {
+                <#inner_type as #krate::StructReflectionHelper>::walk_type(walker, key);
+            },
+            (None, true) => quote! {
+                <#ty as #krate::StructReflectionHelper>::walk_value(value, walker, key);
+            },
+            (None, false) => quote! {
+                <#ty as #krate::StructReflectionHelper>::walk_type(walker, key);
+            },
+        },
+    }
+}
+
+fn has_flattened_field(data: &Data) -> syn::Result<bool> {
+    let fields: Vec<&syn::Field> = match data {
+        Data::Struct(data_struct) => data_struct.fields.iter().collect(),
+        Data::Enum(data_enum) => data_enum
+            .variants
+            .iter()
+            .flat_map(|variant| &variant.fields)
+            .collect(),
+        Data::Union(_) => Vec::new(),
+    };
 
-    let struct_name = input.ident;
-    let generics = input.generics;
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    for field in fields {
+        if FieldAttrs::from_ast(&field.attrs)?.flatten() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
 
-    let generic_types: Vec<_> = generics.type_params().collect();
+fn check_transparent(ident: &syn::Ident, fields: &Fields) -> syn::Result<()> {
+    let mut reflected = 0;
+    for field in fields {
+        let field_attrs = FieldAttrs::from_ast(&field.attrs)?;
+        if field_attrs.prefix().is_some() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`prefix` has no effect in a `transparent` struct",
+            ));
+        }
+        if !field_attrs.skip() {
+            reflected += 1;
+        }
+    }
 
-    let field_list_code = match input.data {
-        Data::Struct(data_struct) => match data_struct.fields {
-            Fields::Named(fields) => fields
-                .named
-                .iter()
-                .map(|field| generate_field_code(field, &generic_types))
-                .collect(),
-            Fields::Unnamed(fields) => fields
-                .unnamed
-                .iter()
-                .enumerate()
-                .map(|(i, field)| generate_unnamed_field_code(i, field, &generic_types))
-                .collect(),
-            Fields::Unit => vec![],
-        },
-        _ => panic!("StructReflection can only be used on structs."),
-    };
+    if reflected != 1 {
+        return Err(syn::Error::new_spanned(
+            ident,
+            "`transparent` requires exactly one field that is not skipped",
+        ));
+    }
+    Ok(())
+}
+
+/// Reports every field whose type cannot be reflected, all at once. Skipped
+/// fields are not reflected and `leaf` fields are never looked into, so any
+/// type is accepted for them.
+fn check_field_types<'a>(fields: impl IntoIterator<Item = &'a syn::Field>) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+
+    for field in fields {
+        let checked = FieldAttrs::from_ast(&field.attrs).and_then(|field_attrs| {
+            if field_attrs.skip() || field_attrs.leaf() {
+                Ok(())
+            } else if field_attrs.elements() && !contains_type(&field.ty, element_type) {
+                Err(syn::Error::new_spanned(
+                    &field.ty,
+                    "`elements` requires a `Vec<T>`, `VecDeque<T>` or slice field",
+                ))
+            } else if field_attrs.values() && !contains_type(&field.ty, map_value_type) {
+                Err(syn::Error::new_spanned(
+                    &field.ty,
+                    "`values` requires a `HashMap`, `BTreeMap` or `IndexMap` field",
+                ))
+            } else {
+                check_supported_type(&field.ty, &field_attrs)
+            }
+        });
 
-    let expanded = quote! {
-        impl #impl_generics StructReflectionHelper for #struct_name #ty_generics #where_clause {
-            fn struct_reflection() -> Option<Vec<String>> {
-                let mut fields = Vec::new();
-                #(#field_list_code)*
-                Some(fields)
+        if let Err(err) = checked {
+            match &mut errors {
+                Some(errors) => errors.combine(err),
+                None => errors = Some(err),
             }
         }
+    }
+
+    match errors {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Rejects the types the generated code has no way to reflect, looking through
+/// the tuples, arrays and options that the macro expands itself, and through
+/// collections and maps when their `elements` or `values` are expanded.
+fn check_supported_type(ty: &syn::Type, field_attrs: &FieldAttrs) -> syn::Result<()> {
+    if let Some(inner_type) = option_inner_type(ty) {
+        return check_supported_type(inner_type, field_attrs);
+    }
+    if let Some(element_type) = element_type(ty).filter(|_| field_attrs.elements()) {
+        return check_supported_type(element_type, field_attrs);
+    }
+    if let Some(value_type) = map_value_type(ty).filter(|_| field_attrs.values()) {
+        return check_supported_type(value_type, field_attrs);
+    }
+
+    let reason = match ungroup(ty) {
+        syn::Type::Tuple(tuple_type) => {
+            return tuple_type
+                .elems
+                .iter()
+                .try_for_each(|elem| check_supported_type(elem, field_attrs))
+        }
+        syn::Type::Array(array_type) => return check_supported_type(&array_type.elem, field_attrs),
+        syn::Type::Path(_) | syn::Type::Slice(_) => return Ok(()),
+        syn::Type::Reference(_) => "reference fields are not supported, use an owned type instead",
+        syn::Type::Ptr(_) => "raw pointer fields are not supported",
+        syn::Type::BareFn(_) => "function pointer fields are not supported",
+        syn::Type::TraitObject(_) => "trait object fields are not supported",
+        syn::Type::ImplTrait(_) => "`impl Trait` is not supported in field position",
+        syn::Type::Never(_) => "the never type `!` is not supported",
+        syn::Type::Infer(_) => "the placeholder type `_` is not supported",
+        syn::Type::Macro(_) => "macros in type position are not supported by StructReflection",
+        _ => "unsupported field type",
     };
 
-    TokenStream::from(expanded)
+    Err(syn::Error::new_spanned(ty, reason))
+}
+
+/// Field settings that apply to every level of the field's type.
+#[derive(Clone, Copy)]
+struct TypeOptions {
+    option_style: OptionStyle,
+    /// Expand collections into their element type (`#[reflect(elements)]`).
+    elements: bool,
+    /// Expand maps into their value type (`#[reflect(values)]`).
+    values: bool,
+    /// Stop at any type that is not a tuple, array or option (`#[reflect(leaf)]`).
+    leaf: bool,
+}
+
+impl TypeOptions {
+    fn new(field_attrs: &FieldAttrs, ctx: &Context) -> Self {
+        TypeOptions {
+            option_style: field_attrs
+                .option_style()
+                .unwrap_or(ctx.container_attrs.option_style()),
+            elements: field_attrs.elements(),
+            values: field_attrs.values(),
+            leaf: field_attrs.leaf(),
+        }
+    }
 }
 
 fn generate_field_code(
+    prefix: proc_macro2::TokenStream,
     field: &syn::Field,
-    generic_types: &[&syn::TypeParam],
+    field_attrs: &FieldAttrs,
+    walk: Walk,
+    binding: Option<&proc_macro2::TokenStream>,
+    ctx: &Context,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let options = TypeOptions::new(field_attrs, ctx);
+
+    Ok(match binding {
+        Some(binding) => {
+            let value_code = generate_value_code(&field.ty, options, walk, ctx);
+            quote! {
+                {
+                    let prefix = #prefix;
+                    let value = #binding;
+                    #value_code
+                }
+            }
+        }
+        None => {
+            let type_code = generate_type_code(&field.ty, options, ctx);
+            quote! {
+                {
+                    let prefix = #prefix;
+                    #type_code
+                }
+            }
+        }
+    })
+}
+
+/// Generates the code pushing the names for a value of type `ty` into `fields`.
+///
+/// The generated code expects a `prefix` in scope holding the `FieldPath` of
+/// the value itself; tuples, arrays and options extend it before recursing.
+fn generate_type_code(
+    ty: &syn::Type,
+    options: TypeOptions,
+    ctx: &Context,
 ) -> proc_macro2::TokenStream {
-    let field_name = field.ident.as_ref().unwrap().to_string();
-    let field_type = &field.ty;
+    let ty = ungroup(ty);
+    let krate = &ctx.krate;
+
+    if let Some(leaf_code) = generate_leaf_code(ty, Walk::Type, ctx) {
+        return leaf_code;
+    }
+
+    if let Some(inner_type) = option_inner_type(ty) {
+        let inner_code = generate_type_code(inner_type, options, ctx);
+        return generate_option_code(inner_type, inner_code, options, Walk::Type, ctx);
+    }
 
-    if is_primitive_type(field_type) {
+    if let Some(element_type) = element_type(ty).filter(|_| options.elements) {
+        let element_code = generate_type_code(element_type, options, ctx);
         return quote! {
-            fields.push(#field_name.to_string());
+            let prefix = prefix.child(#krate::Segment::Element);
+            #element_code
         };
     }
 
-    for generic_type in generic_types {
-        if is_generic_parameter(field_type, generic_type) {
-            return quote! {
-                fields.push(#field_name.to_string());
-            };
+    if let Some(value_type) = map_value_type(ty).filter(|_| options.values) {
+        let value_code = generate_type_code(value_type, options, ctx);
+        return quote! {
+            let prefix = prefix.child(#krate::Segment::AnyKey);
+            #value_code
+        };
+    }
+
+    match ty {
+        syn::Type::Tuple(tuple_type) if tuple_type.elems.is_empty() => quote! {
+            let _ = prefix;
+        },
+        syn::Type::Tuple(tuple_type) => {
+            let element_code = tuple_type.elems.iter().enumerate().map(|(i, elem)| {
+                let elem_code = generate_type_code(elem, options, ctx);
+                quote! {
+                    {
+                        let prefix = prefix.child(#krate::Segment::TupleIndex(#i));
+                        #elem_code
+                    }
+                }
+            });
+            quote! {
+                #(#element_code)*
+            }
+        }
+        syn::Type::Array(array_type) => {
+            let array_len = &array_type.len;
+            let elem_code = generate_type_code(&array_type.elem, options, ctx);
+            quote! {
+                for i in 0..(#array_len) {
+                    let prefix = prefix.child(#krate::Segment::ArrayIndex(i));
+                    #elem_code
+                }
+            }
+        }
+        _ if options.leaf => generate_opaque_leaf_code(ty, Walk::Type, ctx),
+        _ => {
+            let helper_type = boxed_type(ty).unwrap_or(ty);
+            let helper_code = generate_helper_code(
+                ty,
+                quote!(<#helper_type as #krate::StructReflectionHelper>::field_infos()),
+                Walk::Type,
+                ctx,
+            );
+            // Collections that are a single leaf still lead to the types they
+            // hold in the type graph
+            let mut probed = Vec::new();
+            if let Some(held_type) = element_type(ty).or_else(|| map_value_type(ty)) {
+                collect_probed_types(held_type, ctx, &mut probed);
+            }
+            if probed.is_empty() {
+                return helper_code;
+            }
+            quote! {
+                {
+                    use #krate::{ProbeOpaque as _, ProbeReflected as _};
+                    #((&#krate::ElementProbe::<#probed>::new()).probe_graph();)*
+                }
+                #helper_code
+            }
         }
     }
+}
 
-    if let Some(token_stream) = handle_tuple_type(field_type, &field_name, generic_types) {
-        return token_stream;
+/// Collects the types to look through in `ty`, held by a collection, for the
+/// type graph: those inside its options, tuples, arrays and collections, which
+/// the runtime impls cannot see into.
+fn collect_probed_types<'a>(ty: &'a syn::Type, ctx: &Context, probed: &mut Vec<&'a syn::Type>) {
+    let ty = ungroup(ty);
+    if ctx.is_generic_type(ty) {
+        return;
     }
 
-    if let Some(token_stream) = handle_array_type(field_type, &field_name, generic_types) {
-        return token_stream;
+    if let Some(inner_type) = option_inner_type(ty)
+        .or_else(|| element_type(ty))
+        .or_else(|| map_value_type(ty))
+        .or_else(|| boxed_type(ty))
+    {
+        return collect_probed_types(inner_type, ctx, probed);
     }
 
-    quote! {
-        if let Some(inner_fields) = <#field_type as StructReflectionHelper>::struct_reflection() {
-            for inner_field in inner_fields {
-                fields.push(format!("{}__{}",  #field_name, inner_field));
+    match ty {
+        syn::Type::Tuple(tuple_type) => {
+            for elem in &tuple_type.elems {
+                collect_probed_types(elem, ctx, probed);
             }
-        } else {
-            fields.push(#field_name.to_string());
         }
+        syn::Type::Array(array_type) => collect_probed_types(&array_type.elem, ctx, probed),
+        _ => probed.push(ty),
     }
 }
 
-fn generate_unnamed_field_code(
-    index: usize,
-    field: &syn::Field,
-    generic_types: &[&syn::TypeParam],
+/// Generates the code pushing the entries for the value of type `ty` borrowed
+/// by `value`.
+///
+/// Unlike [`generate_type_code`], map keys and collection elements are taken
+/// from the value, and options that hold a value are walked into.
+fn generate_value_code(
+    ty: &syn::Type,
+    options: TypeOptions,
+    walk: Walk,
+    ctx: &Context,
 ) -> proc_macro2::TokenStream {
-    let field_type = &field.ty;
-    let index_str = index.to_string();
+    let ty = ungroup(ty);
+    let krate = &ctx.krate;
 
-    if is_primitive_type(field_type) {
+    if let Some(leaf_code) = generate_leaf_code(ty, walk, ctx) {
         return quote! {
-            fields.push(#index_str.to_string());
+            let _ = value;
+            #leaf_code
         };
     }
 
-    for generic_type in generic_types {
-        if is_generic_parameter(field_type, generic_type) {
-            return quote! {
-                fields.push(#index_str.to_string());
-            };
-        }
+    if let Some(inner_type) = option_inner_type(ty) {
+        let inner_value_code = generate_value_code(inner_type, options, walk, ctx);
+        // `None` has no values, only names, but a leaf can still be assigned
+        let inner_type_code = match walk {
+            Walk::Values => quote!(),
+            Walk::ValuesMut => generate_unset_code(inner_type, options, ctx),
+            Walk::Type | Walk::Instance => generate_type_code(inner_type, options, ctx),
+        };
+        let inner_code = quote! {
+            match value {
+                ::std::option::Option::Some(value) => {
+                    #inner_value_code
+                }
+                option => {
+                    let _ = option;
+                    #inner_type_code
+                }
+            }
+        };
+        let option_code = generate_option_code(inner_type, inner_code, options, walk, ctx);
+        return quote! {
+            let _ = value;
+            #option_code
+        };
     }
 
-    if let Some(token_stream) = handle_tuple_type(field_type, &index_str, generic_types) {
-        return token_stream;
+    let iter = walk.iter();
+    if let Some(element_type) = element_type(ty).filter(|_| options.elements) {
+        let element_code = generate_value_code(element_type, options, walk, ctx);
+        return quote! {
+            for (i, value) in value.#iter().enumerate() {
+                let prefix = prefix.child(#krate::Segment::ArrayIndex(i));
+                #element_code
+            }
+        };
     }
 
-    if let Some(token_stream) = handle_array_type(field_type, &index_str, generic_types) {
-        return token_stream;
+    if let Some(value_type) = map_value_type(ty).filter(|_| options.values) {
+        let value_code = generate_value_code(value_type, options, walk, ctx);
+        return quote! {
+            for (key, value) in value.#iter() {
+                let prefix = prefix.child(#krate::Segment::Key(::std::string::ToString::to_string(key)));
+                #value_code
+            }
+        };
     }
 
-    quote! {
-        if let Some(inner_fields) = <#field_type as StructReflectionHelper>::struct_reflection() {
-            for inner_field in inner_fields {
-                fields.push(format!("{}__{}", #index, inner_field));
+    match ty {
+        syn::Type::Tuple(tuple_type) if tuple_type.elems.is_empty() => quote! {
+            let _ = (prefix, value);
+        },
+        syn::Type::Tuple(tuple_type) => {
+            let reference = walk.reference();
+            let element_code = tuple_type.elems.iter().enumerate().map(|(i, elem)| {
+                let index = syn::Index::from(i);
+                let elem_code = generate_value_code(elem, options, walk, ctx);
+                quote! {
+                    {
+                        let prefix = prefix.child(#krate::Segment::TupleIndex(#i));
+                        let value = #reference value.#index;
+                        #elem_code
+                    }
+                }
+            });
+            quote! {
+                #(#element_code)*
             }
-        } else {
-            fields.push(#index.to_string());
         }
-    }
-}
-
-// Helper functions for type checking
-fn is_primitive_type(ty: &syn::Type) -> bool {
-    if let syn::Type::Path(type_path) = ty {
-        if let Some(segment) = type_path.path.segments.last() {
-            let type_name = &segment.ident.to_string();
-            return matches!(
-                type_name.as_str(),
-                "bool"
-                    | "char"
-                    | "u8"
-                    | "u16"
-                    | "u32"
-                    | "u64"
-                    | "u128"
-                    | "usize"
-                    | "i8"
-                    | "i16"
-                    | "i32"
-                    | "i64"
-                    | "i128"
-                    | "isize"
-                    | "f32"
-                    | "f64"
-                    | "str"
-                    | "String"
-            );
+        syn::Type::Array(array_type) => {
+            let elem_code = generate_value_code(&array_type.elem, options, walk, ctx);
+            quote! {
+                for (i, value) in value.#iter().enumerate() {
+                    let prefix = prefix.child(#krate::Segment::ArrayIndex(i));
+                    #elem_code
+                }
+            }
+        }
+        _ if options.leaf => {
+            let leaf_code = generate_opaque_leaf_code(ty, walk, ctx);
+            quote! {
+                let _ = value;
+                #leaf_code
+            }
+        }
+        _ => {
+            let entries = match (boxed_type(ty), walk) {
+                (Some(inner_type), Walk::Type | Walk::Instance) => quote! {
+                    <#inner_type as #krate::StructReflectionHelper>::instance_field_infos(&**value)
+                },
+                (Some(inner_type), Walk::Values) => quote! {
+                    <#inner_type as #krate::StructReflectionHelper>::boxed_field_values(value)
+                },
+                (Some(inner_type), Walk::ValuesMut) => quote! {
+                    <#inner_type as #krate::StructReflectionHelper>::boxed_field_values_mut(value)
+                },
+                (None, Walk::Type | Walk::Instance) => quote! {
+                    <#ty as #krate::StructReflectionHelper>::instance_field_infos(value)
+                },
+                (None, Walk::Values) => quote! {
+                    <#ty as #krate::StructReflectionHelper>::field_values(value)
+                },
+                (None, Walk::ValuesMut) => quote! {
+                    <#ty as #krate::StructReflectionHelper>::field_values_mut(value)
+                },
+            };
+            generate_helper_code(ty, entries, walk, ctx)
         }
     }
-    false
 }
 
-fn is_generic_parameter(ty: &syn::Type, generic_type: &syn::TypeParam) -> bool {
-    if let syn::Type::Path(type_path) = ty {
-        return type_path.path.is_ident(&generic_type.ident);
-    }
-    false
-}
+/// Code for the assignable entries of `option`, an `Option<ty>` holding
+/// `None`: only a leaf can be set from a single value.
+fn generate_unset_code(
+    ty: &syn::Type,
+    options: TypeOptions,
+    ctx: &Context,
+) -> proc_macro2::TokenStream {
+    let ty = ungroup(ty);
+    let krate = &ctx.krate;
 
-fn is_tuple_type(ty: &syn::Type) -> bool {
-    if let syn::Type::Tuple(_) = ty {
-        return true;
+    // Nor can a box, which is not a leaf of its own
+    let expanded = option_inner_type(ty).is_some()
+        || element_type(ty).is_some_and(|_| options.elements)
+        || map_value_type(ty).is_some_and(|_| options.values)
+        || matches!(ty, syn::Type::Tuple(_) | syn::Type::Array(_));
+    if expanded || boxed_type(ty).is_some() {
+        quote!()
+    } else if ctx.is_generic_type(ty) || options.leaf {
+        quote! {
+            fields.push(#krate::FieldMut::unset(prefix, option));
+        }
+    } else {
+        generate_helper_code(
+            ty,
+            quote!(<#ty as #krate::StructReflectionHelper>::unset_values_mut(option)),
+            Walk::ValuesMut,
+            ctx,
+        )
     }
-    false
 }
 
-fn handle_tuple_type(
+/// Code for the deriving type's generic parameters, which are always a single
+/// leaf: they have no `StructReflectionHelper` bound to ask for their fields.
+fn generate_leaf_code(
     ty: &syn::Type,
-    field_name: &str,
-    generic_types: &[&syn::TypeParam],
+    walk: Walk,
+    ctx: &Context,
 ) -> Option<proc_macro2::TokenStream> {
-    if let syn::Type::Tuple(tuple_type) = ty {
-        let mut element_handling = Vec::new();
-
-        // Generate code for each element of the tuple
-        for (i, elem) in tuple_type.elems.iter().enumerate() {
-            let index = proc_macro2::Literal::usize_unsuffixed(i);
-
-            // Check if element is a generic parameter
-            let mut is_generic = false;
-            for generic_type in generic_types {
-                if is_generic_parameter(elem, generic_type) {
-                    is_generic = true;
-                    element_handling.push(quote! {
-                        fields.push(format!("{}__{}",  #field_name, #index));
-                    });
-                    break;
-                }
-            }
+    let krate = &ctx.krate;
 
-            // If not a generic parameter, try to handle recursively
-            if !is_generic {
-                element_handling.push(quote! {
-                    if let Some(inner_fields) = <#elem as StructReflectionHelper>::struct_reflection() {
-                        for inner_field in inner_fields {
-                            fields.push(format!("{}__{}__{}",  #field_name, #index, inner_field));
-                        }
-                    } else {
-                        fields.push(format!("{}__{}",  #field_name, #index));
-                    }
+    // Generic parameters are only known once instantiated
+    if ctx.is_generic_type(ty) {
+        return Some(match walk {
+            Walk::Type | Walk::Instance => quote! {
+                fields.push(#krate::FieldInfo {
+                    path: prefix,
+                    type_name: ::std::any::type_name::<#ty>(),
+                    kind: #krate::PrimitiveKind::from_type_name(::std::any::type_name::<#ty>()),
+                    is_generic: true,
                 });
-            }
-        }
-
-        return Some(quote! {
-            #(#element_handling)*
+            },
+            Walk::Values | Walk::ValuesMut => generate_field_ref_code(walk, ctx),
         });
     }
+
     None
 }
 
-// CORRECTED FUNCTION: Handle array types properly
-fn handle_array_type(
+/// Code for any other type, which is asked for its entries through the
+/// `StructReflectionHelper` call `entries`, and is a leaf if it has none.
+fn generate_helper_code(
     ty: &syn::Type,
-    field_name: &str,
-    generic_types: &[&syn::TypeParam],
-) -> Option<proc_macro2::TokenStream> {
-    if let syn::Type::Array(array_type) = ty {
-        let elem_type = &*array_type.elem;
-        let array_len = &array_type.len;
-
-        // Case 1: Array of tuples like [(T, U); N]
-        if is_tuple_type(elem_type) {
-            if let syn::Type::Tuple(tuple_type) = elem_type {
-                let tuple_size = tuple_type.elems.len();
-
-                return Some(quote! {
-                    for i in 0..#array_len {
-                        for j in 0..#tuple_size {
-                            fields.push(format!("{}__{}__{}",  #field_name, i, j));
-                        }
-                    }
-                });
+    entries: proc_macro2::TokenStream,
+    walk: Walk,
+    ctx: &Context,
+) -> proc_macro2::TokenStream {
+    // Types that can be borrowed do so themselves, as `ty` may be unsized
+    let leaf_code = if walk.borrows_values() {
+        quote!()
+    } else {
+        generate_opaque_leaf_code(ty, walk, ctx)
+    };
+
+    quote! {
+        match #entries {
+            ::std::option::Option::Some(inner_fields) => {
+                for mut inner_field in inner_fields {
+                    let mut path = prefix.clone();
+                    path.extend(inner_field.path);
+                    inner_field.path = path;
+                    fields.push(inner_field);
+                }
+            }
+            ::std::option::Option::None => {
+                #leaf_code
             }
         }
+    }
+}
+
+/// Code for a single entry of type `ty` that nothing is known about, used for
+/// types without fields and for `#[reflect(leaf)]` fields.
+fn generate_opaque_leaf_code(
+    ty: &syn::Type,
+    walk: Walk,
+    ctx: &Context,
+) -> proc_macro2::TokenStream {
+    let krate = &ctx.krate;
 
-        // Case 2: Nested arrays with tuples like [[(T, U); M]; N]
-        if let syn::Type::Array(inner_array_type) = elem_type {
-            let inner_elem_type = &*inner_array_type.elem;
-            let inner_array_len = &inner_array_type.len;
+    match walk {
+        Walk::Type | Walk::Instance => quote! {
+            fields.push(#krate::FieldInfo {
+                path: prefix,
+                type_name: ::std::any::type_name::<#ty>(),
+                kind: #krate::PrimitiveKind::Other,
+                is_generic: false,
+            });
+        },
+        Walk::Values | Walk::ValuesMut => generate_field_ref_code(walk, ctx),
+    }
+}
 
-            if is_tuple_type(inner_elem_type) {
-                if let syn::Type::Tuple(tuple_type) = inner_elem_type {
-                    let tuple_size = tuple_type.elems.len();
+/// Code for a leaf borrowing `value`.
+fn generate_field_ref_code(walk: Walk, ctx: &Context) -> proc_macro2::TokenStream {
+    let krate = &ctx.krate;
 
-                    return Some(quote! {
-                        for i in 0..#array_len {
-                            for j in 0..#inner_array_len {
-                                for k in 0..#tuple_size {
-                                    fields.push(format!("{}__{}__{}__{}", #field_name, i, j, k));
-                                }
-                            }
-                        }
-                    });
+    match walk {
+        Walk::ValuesMut => quote! {
+            fields.push(#krate::FieldMut::new(prefix, value));
+        },
+        Walk::Type | Walk::Instance | Walk::Values => quote! {
+            fields.push(#krate::FieldRef {
+                path: prefix,
+                value,
+            });
+        },
+    }
+}
+
+// `Option<T>` is recognised syntactically so that the inner type's own fields
+// can be reflected; the runtime `Option<T>` impl cannot see them without
+// specialization. `inner_code` pushes the entries for `T`.
+fn generate_option_code(
+    inner_type: &syn::Type,
+    inner_code: proc_macro2::TokenStream,
+    options: TypeOptions,
+    walk: Walk,
+    ctx: &Context,
+) -> proc_macro2::TokenStream {
+    let krate = &ctx.krate;
+    let entry = walk.entry(ctx);
+
+    match options.option_style {
+        OptionStyle::Suffix => quote! {
+            {
+                let mut option_fields: ::std::vec::Vec<#entry> = ::std::vec::Vec::new();
+                {
+                    let fields = &mut option_fields;
+                    #inner_code
+                }
+                for mut option_field in option_fields {
+                    option_field.path.push(#krate::Segment::OptionalMarker);
+                    fields.push(option_field);
                 }
             }
-        }
-
-        // Case 3: Check if array element is primitive type
-        if is_primitive_type(elem_type) {
-            return Some(quote! {
-                for i in 0..#array_len {
-                    fields.push(format!("{}__{}", #field_name, i));
+        },
+        OptionStyle::Prefix => quote! {
+            {
+                let prefix = prefix.child(#krate::Segment::OptionalMarker);
+                #inner_code
+            }
+        },
+        // The whole option is the leaf. Reads borrow the value it holds, and
+        // leave it out when it holds `None`, like the fields of expanded
+        // options, while assigning it sets it to `Some`.
+        OptionStyle::Collapse if walk == Walk::Values => {
+            let field_ref_code = generate_field_ref_code(walk, ctx);
+            quote! {
+                if let ::std::option::Option::Some(value) = value {
+                    let prefix = prefix.child(#krate::Segment::OptionalMarker);
+                    #field_ref_code
                 }
-            });
+            }
         }
-
-        // Case 4: Array of generic type [T; N]
-        for generic_type in generic_types {
-            if is_generic_parameter(elem_type, generic_type) {
-                return Some(quote! {
-                    for i in 0..#array_len {
-                        fields.push(format!("{}__{}", #field_name, i));
+        OptionStyle::Collapse if walk == Walk::ValuesMut => {
+            let field_code = if ctx.is_generic_type(inner_type)
+                || options.leaf
+                || boxed_type(inner_type).is_some()
+            {
+                quote! {
+                    match value {
+                        ::std::option::Option::Some(value) => #krate::FieldMut::new(prefix, value),
+                        option => #krate::FieldMut::unset(prefix, option),
+                    }
+                }
+            } else {
+                quote! {
+                    {
+                        let mut field = <#inner_type as #krate::StructReflectionHelper>::collapsed_value_mut(value);
+                        field.path = prefix;
+                        field
                     }
+                }
+            };
+            quote! {
+                let prefix = prefix.child(#krate::Segment::OptionalMarker);
+                fields.push(#field_code);
+            }
+        }
+        OptionStyle::Collapse => {
+            let is_generic = ctx.is_generic_type(inner_type);
+            quote! {
+                fields.push(#krate::FieldInfo {
+                    path: prefix.child(#krate::Segment::OptionalMarker),
+                    type_name: ::std::any::type_name::<#inner_type>(),
+                    kind: #krate::PrimitiveKind::from_type_name(::std::any::type_name::<#inner_type>()),
+                    is_generic: #is_generic,
                 });
             }
         }
+    }
+}
 
-        // Case 5: Nested array [[ElementType; M]; N]
-        if let syn::Type::Array(inner_array_type) = elem_type {
-            let inner_elem_type = &*inner_array_type.elem;
-            let inner_array_len = &inner_array_type.len;
+// Helper functions for type checking
 
-            // Case 5.1: If inner element is primitive
-            if is_primitive_type(inner_elem_type) {
-                return Some(quote! {
-                    for i in 0..#array_len {
-                        for j in 0..#inner_array_len {
-                            fields.push(format!("{}__{}__{}", #field_name, i, j));
-                        }
-                    }
-                });
-            }
+/// Whether `ty` is unsized by its syntax: slices and trait objects. Other
+/// unsized types, such as `str`, are only known by their name, which any type
+/// may share; structs ending with one are marked `#[reflect(unsized)]`.
+fn is_unsized_type(ty: &syn::Type) -> bool {
+    matches!(ungroup(ty), syn::Type::Slice(_) | syn::Type::TraitObject(_))
+}
 
-            // Case 5.2: If inner element is generic
-            for generic_type in generic_types {
-                if is_generic_parameter(inner_elem_type, generic_type) {
-                    return Some(quote! {
-                        for i in 0..#array_len {
-                            for j in 0..#inner_array_len {
-                                fields.push(format!("{}__{}__{}", #field_name, i, j));
-                            }
-                        }
-                    });
-                }
+// Types coming from macro_rules fragments (`$ty:ty`) are wrapped in invisible groups
+fn ungroup(ty: &syn::Type) -> &syn::Type {
+    match ty {
+        syn::Type::Group(group) => ungroup(&group.elem),
+        syn::Type::Paren(paren) => ungroup(&paren.elem),
+        _ => ty,
+    }
+}
+
+fn is_generic_parameter(ty: &syn::Type, generic_type: &syn::TypeParam) -> bool {
+    if let syn::Type::Path(type_path) = ungroup(ty) {
+        return type_path.path.is_ident(&generic_type.ident);
+    }
+    false
+}
+
+/// Returns `T` for the collections whose elements `#[reflect(elements)]` can
+/// expand: `Vec<T>`, `VecDeque<T>`, `[T]` and `Box<[T]>`.
+fn element_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let type_path = match ungroup(ty) {
+        syn::Type::Slice(slice_type) => return Some(&slice_type.elem),
+        syn::Type::Path(type_path) if type_path.qself.is_none() => type_path,
+        _ => return None,
+    };
+
+    let segment = type_path.path.segments.last()?;
+    let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    let Some(syn::GenericArgument::Type(argument)) = arguments.args.first() else {
+        return None;
+    };
+    if arguments.args.len() != 1 {
+        return None;
+    }
+
+    if segment.ident == "Vec" || segment.ident == "VecDeque" {
+        Some(argument)
+    } else if segment.ident == "Box" {
+        match ungroup(argument) {
+            syn::Type::Slice(slice_type) => Some(&slice_type.elem),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// Returns `V` for the maps whose values `#[reflect(values)]` can expand:
+/// `HashMap<K, V>`, `BTreeMap<K, V>` and `IndexMap<K, V>`.
+fn map_value_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(type_path) = ungroup(ty) else {
+        return None;
+    };
+    if type_path.qself.is_some() {
+        return None;
+    }
+
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "HashMap" && segment.ident != "BTreeMap" && segment.ident != "IndexMap" {
+        return None;
+    }
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(arguments) if arguments.args.len() >= 2 => {
+            match arguments.args.iter().nth(1) {
+                Some(syn::GenericArgument::Type(value_type)) => Some(value_type),
+                _ => None,
             }
         }
+        _ => None,
+    }
+}
 
-        // Default case for arrays: Try to get nested fields or use basic indexing
-        return Some(quote! {
-            if let Some(sub_fields) = <#elem_type as StructReflectionHelper>::struct_reflection() {
-                for i in 0..#array_len {
-                    for sub_field in &sub_fields {
-                        fields.push(format!("{}__{}__{}", #field_name, i, sub_field));
-                    }
-                }
-            } else {
-                for i in 0..#array_len {
-                    fields.push(format!("{}__{}", #field_name, i));
-                }
+/// Whether `find` matches `ty` or a type nested in its tuples, arrays and options.
+fn contains_type(ty: &syn::Type, find: fn(&syn::Type) -> Option<&syn::Type>) -> bool {
+    if find(ty).is_some() {
+        return true;
+    }
+    if let Some(inner_type) = option_inner_type(ty) {
+        return contains_type(inner_type, find);
+    }
+    match ungroup(ty) {
+        syn::Type::Tuple(tuple_type) => tuple_type
+            .elems
+            .iter()
+            .any(|elem| contains_type(elem, find)),
+        syn::Type::Array(array_type) => contains_type(&array_type.elem, find),
+        _ => false,
+    }
+}
+
+/// Returns `T` for types written as `Option<T>` (or a path ending in it).
+fn option_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    wrapped_type(ty, "Option")
+}
+
+/// Returns `T` for types written as `Box<T>` (or a path ending in it), which
+/// are reflected through the `T` they hold: a box only implements
+/// `StructReflection` around a `StructReflection` type.
+fn boxed_type(ty: &syn::Type) -> Option<&syn::Type> {
+    wrapped_type(ty, "Box")
+}
+
+/// Returns `T` for types written as `wrapper<T>` (or a path ending in it).
+fn wrapped_type<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
+    let syn::Type::Path(type_path) = ungroup(ty) else {
+        return None;
+    };
+    if type_path.qself.is_some() {
+        return None;
+    }
+
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1 => {
+            match arguments.args.first() {
+                Some(syn::GenericArgument::Type(inner_type)) => Some(inner_type),
+                _ => None,
             }
-        });
+        }
+        _ => None,
     }
-    None
 }
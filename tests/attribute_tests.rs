@@ -0,0 +1,252 @@
+#![allow(dead_code)]
+
+use std::marker::PhantomData;
+
+use struct_reflection::StructReflection;
+
+// A facade crate re-exporting struct_reflection under another path
+mod facade {
+    pub use struct_reflection as reflection;
+}
+
+#[derive(StructReflection)]
+#[reflect(crate = "facade::reflection")]
+struct ReexportedStruct {
+    id: u64,
+    values: [i32; 2],
+}
+
+// Structs declared from inside a macro_rules expansion
+macro_rules! declare_struct {
+    ($name:ident, $ty:ty) => {
+        #[derive(StructReflection)]
+        struct $name {
+            first: $ty,
+            second: [$ty; 2],
+        }
+    };
+}
+
+declare_struct!(MacroStruct, u8);
+
+#[test]
+fn test_crate_path_override() {
+    let names = ReexportedStruct::struct_reflection();
+    assert_eq!(names.unwrap(), vec!["id", "values__0", "values__1"]);
+}
+
+#[test]
+fn test_struct_declared_in_macro_rules() {
+    let names = MacroStruct::struct_reflection();
+    assert_eq!(names.unwrap(), vec!["first", "second__0", "second__1"]);
+}
+
+#[derive(StructReflection)]
+struct Inner {
+    a: i32,
+    b: bool,
+}
+
+// Container-wide option style, overridden on a single field
+#[derive(StructReflection)]
+#[reflect(option = "prefix")]
+struct OptionStyles {
+    prefixed: Option<Inner>,
+    #[reflect(option = "collapse")]
+    collapsed: Option<Inner>,
+    #[reflect(option = "suffix")]
+    suffixed: Option<Option<Inner>>,
+    primitive: Option<u8>,
+}
+
+#[test]
+fn test_option_styles() {
+    let names = OptionStyles::struct_reflection();
+    assert_eq!(
+        names.unwrap(),
+        vec![
+            "prefixed__optional__a",
+            "prefixed__optional__b",
+            "collapsed__optional",
+            "suffixed__a__optional__optional",
+            "suffixed__b__optional__optional",
+            "primitive__optional"
+        ]
+    );
+}
+
+#[derive(StructReflection)]
+struct GeoPoint {
+    latitude: f64,
+    longitude: f64,
+}
+
+// Container-wide case conversion, with a per-field rename taking precedence
+#[derive(StructReflection)]
+#[reflect(rename_all = "camelCase")]
+struct CamelCaseStruct {
+    user_id: u64,
+    home_location: GeoPoint,
+    recent_scores: [u8; 2],
+    min_max_pair: (i32, i32),
+    #[reflect(rename = "e-mail")]
+    email_address: String,
+    r#type: String,
+}
+
+#[derive(StructReflection)]
+#[reflect(rename_all = "SCREAMING_SNAKE_CASE")]
+struct EnvConfig {
+    database_url: String,
+    max_connections: u32,
+    fallback: Option<GeoPoint>,
+}
+
+#[derive(StructReflection)]
+struct RenamedTuple(#[reflect(rename = "id")] u64, [bool; 2]);
+
+#[derive(StructReflection)]
+#[reflect(rename_all = "snake_case", enum_strategy = "prefixed")]
+enum HttpEvent {
+    HTTPRequest {
+        request_path: String,
+    },
+    #[reflect(rename = "resp")]
+    Response {
+        status_code: u16,
+    },
+    Timeout,
+}
+
+#[test]
+fn test_rename_all_camel_case() {
+    let names = CamelCaseStruct::struct_reflection();
+    assert_eq!(
+        names.unwrap(),
+        vec![
+            "userId",
+            "homeLocation__latitude",
+            "homeLocation__longitude",
+            "recentScores__0",
+            "recentScores__1",
+            "minMaxPair__0",
+            "minMaxPair__1",
+            "e-mail",
+            "type"
+        ]
+    );
+}
+
+#[test]
+fn test_rename_all_screaming_snake_case() {
+    let names = EnvConfig::struct_reflection();
+    assert_eq!(
+        names.unwrap(),
+        vec![
+            "DATABASE_URL",
+            "MAX_CONNECTIONS",
+            "FALLBACK__latitude__optional",
+            "FALLBACK__longitude__optional"
+        ]
+    );
+}
+
+#[test]
+fn test_rename_tuple_field() {
+    let names = RenamedTuple::struct_reflection();
+    assert_eq!(names.unwrap(), vec!["id", "1__0", "1__1"]);
+}
+
+#[test]
+fn test_rename_enum_variants() {
+    let names = HttpEvent::struct_reflection();
+    assert_eq!(
+        names.unwrap(),
+        vec!["http_request__request_path", "resp__status_code", "timeout"]
+    );
+}
+
+// Skipped fields, including types that could not be reflected
+#[derive(StructReflection)]
+struct SkippedFields<T> {
+    id: u64,
+    #[reflect(skip)]
+    cache: std::cell::RefCell<Vec<u8>>,
+    #[reflect(skip)]
+    handle: *const u8,
+    #[reflect(skip)]
+    _phantom: PhantomData<T>,
+    #[reflect(skip_if(test))]
+    test_only: bool,
+    #[reflect(skip_if(not(test)))]
+    always_in_tests: bool,
+}
+
+#[derive(StructReflection)]
+struct SkippedTupleField(u8, #[reflect(skip)] String, u16);
+
+#[test]
+fn test_skip_fields() {
+    let names = SkippedFields::<i32>::struct_reflection();
+    assert_eq!(names.unwrap(), vec!["id", "always_in_tests"]);
+}
+
+#[test]
+fn test_skip_tuple_field_keeps_indices() {
+    let names = SkippedTupleField::struct_reflection();
+    assert_eq!(names.unwrap(), vec!["0", "2"]);
+}
+
+// Container-wide separator; nested names use the outer struct's separator
+#[derive(StructReflection)]
+#[reflect(separator = ".")]
+struct DottedStruct {
+    location: GeoPoint,
+    tags: [String; 2],
+    maybe: Option<Inner>,
+}
+
+// Field names that themselves contain the default separator
+#[derive(StructReflection)]
+struct UnderscoredNames {
+    #[reflect(rename = "a__b")]
+    first: u8,
+    nested: Inner,
+}
+
+#[derive(StructReflection)]
+struct OuterTuple((u8, u8), Inner);
+
+#[test]
+fn test_container_separator() {
+    let names = DottedStruct::struct_reflection();
+    assert_eq!(
+        names.unwrap(),
+        vec![
+            "location.latitude",
+            "location.longitude",
+            "tags.0",
+            "tags.1",
+            "maybe.a.optional",
+            "maybe.b.optional"
+        ]
+    );
+}
+
+#[test]
+fn test_runtime_separator() {
+    let names = OuterTuple::struct_reflection_with_separator("/");
+    assert_eq!(names.unwrap(), vec!["0/0", "0/1", "1/a", "1/b"]);
+
+    let names = UnderscoredNames::struct_reflection_with_separator("_");
+    assert_eq!(names.unwrap(), vec!["a__b", "nested_a", "nested_b"]);
+}
+
+#[test]
+fn test_struct_reflection_segments() {
+    let segments = UnderscoredNames::struct_reflection_segments();
+    assert_eq!(
+        segments.unwrap(),
+        vec![vec!["a__b"], vec!["nested", "a"], vec!["nested", "b"]]
+    );
+}
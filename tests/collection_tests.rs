@@ -0,0 +1,108 @@
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+
+use struct_reflection::{FieldPath, PrimitiveKind, Segment, StructReflection};
+
+#[derive(StructReflection)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(StructReflection)]
+struct Polygon {
+    name: String,
+    bytes: Vec<u8>,
+    points: Vec<Point>,
+    history: VecDeque<Point>,
+    labels: Box<[String]>,
+}
+
+#[derive(StructReflection)]
+struct Path {
+    #[reflect(elements)]
+    points: Vec<Point>,
+    #[reflect(elements)]
+    queue: VecDeque<(u8, bool)>,
+    #[reflect(elements)]
+    weights: Box<[f32]>,
+    #[reflect(elements)]
+    waypoints: Option<Vec<Option<Point>>>,
+    #[reflect(elements)]
+    grid: Vec<Vec<u8>>,
+}
+
+#[derive(StructReflection)]
+struct Packet {
+    id: u32,
+    payload: [u8],
+}
+
+#[derive(StructReflection)]
+#[reflect(unsized)]
+struct Label {
+    id: u32,
+    text: str,
+}
+
+#[test]
+fn test_collections_are_leaves() {
+    assert_eq!(
+        Polygon::struct_reflection().unwrap(),
+        vec!["name", "bytes", "points", "history", "labels"]
+    );
+
+    let fields = Polygon::struct_reflection_fields().unwrap();
+    assert_eq!(fields[2].type_name, std::any::type_name::<Vec<Point>>());
+    assert_eq!(fields[2].kind, PrimitiveKind::Other);
+}
+
+#[test]
+fn test_expanded_elements() {
+    assert_eq!(
+        Path::struct_reflection().unwrap(),
+        vec![
+            "points__[]__x",
+            "points__[]__y",
+            "queue__[]__0",
+            "queue__[]__1",
+            "weights__[]",
+            "waypoints__[]__x__optional__optional",
+            "waypoints__[]__y__optional__optional",
+            "grid__[]__[]",
+        ]
+    );
+
+    let fields = Path::struct_reflection_fields().unwrap();
+    assert_eq!(fields[0].kind, PrimitiveKind::Float);
+    assert_eq!(fields[4].type_name, "f32");
+}
+
+#[test]
+fn test_element_segments() {
+    let paths = Path::struct_reflection_paths().unwrap();
+    assert_eq!(
+        paths[0].segments(),
+        &[
+            Segment::Named("points".to_string()),
+            Segment::Element,
+            Segment::Named("x".to_string()),
+        ]
+    );
+    assert_eq!(paths[0].to_string(), "points[].x");
+    assert_eq!(paths[7].to_string(), "grid[][]");
+
+    let parsed: FieldPath = "points[].x".parse().unwrap();
+    assert_eq!(parsed, paths[0]);
+}
+
+#[test]
+fn test_unsized_slice_field() {
+    assert_eq!(Packet::struct_reflection().unwrap(), vec!["id", "payload"]);
+}
+
+#[test]
+fn test_unsized_struct_attribute() {
+    assert_eq!(Label::struct_reflection().unwrap(), vec!["id", "text"]);
+}
@@ -0,0 +1,107 @@
+#![allow(dead_code)]
+
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+// Enum using the default (prefixed) strategy
+#[derive(StructReflection)]
+enum Shape {
+    Circle { center: Point, radius: f64 },
+    Rectangle { corners: [Point; 2] },
+    Segment(Point, Point),
+    Empty,
+}
+
+// Enum listing only its variant names
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "variants")]
+enum Status {
+    Active,
+    Suspended { reason: String },
+    Deleted(u64),
+}
+
+// Enum reflected as a discriminant column plus the union of variant fields
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "union")]
+enum Event {
+    Click { x: i32, y: i32 },
+    Scroll { y: i32, delta: f32 },
+    Close,
+}
+
+// Union strategy with a custom discriminant column name
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "union", tag = "kind")]
+enum Payment<T> {
+    Card { number: String, amount: T },
+    Cash { amount: T },
+}
+
+// Enum nested inside a struct
+#[derive(StructReflection)]
+struct Drawing {
+    name: String,
+    shapes: [Shape; 2],
+    status: Status,
+}
+
+#[test]
+fn test_prefixed_enum() {
+    let names = Shape::struct_reflection();
+    assert_eq!(
+        names.unwrap(),
+        vec![
+            "Circle__center__x",
+            "Circle__center__y",
+            "Circle__radius",
+            "Rectangle__corners__0__x",
+            "Rectangle__corners__0__y",
+            "Rectangle__corners__1__x",
+            "Rectangle__corners__1__y",
+            "Segment__0__x",
+            "Segment__0__y",
+            "Segment__1__x",
+            "Segment__1__y",
+            "Empty"
+        ]
+    );
+}
+
+#[test]
+fn test_variants_enum() {
+    let names = Status::struct_reflection();
+    assert_eq!(names.unwrap(), vec!["Active", "Suspended", "Deleted"]);
+}
+
+#[test]
+fn test_union_enum() {
+    let names = Event::struct_reflection();
+    assert_eq!(names.unwrap(), vec!["variant", "x", "y", "delta"]);
+}
+
+#[test]
+fn test_union_enum_with_tag() {
+    let names = Payment::<u64>::struct_reflection();
+    assert_eq!(names.unwrap(), vec!["kind", "number", "amount"]);
+}
+
+#[test]
+fn test_enum_nested_in_struct() {
+    let names = Drawing::struct_reflection();
+    let names = names.unwrap();
+    assert_eq!(names.len(), 1 + 2 * 12 + 3);
+    assert_eq!(names[0], "name");
+    assert_eq!(names[1], "shapes__0__Circle__center__x");
+    assert_eq!(names[12], "shapes__0__Empty");
+    assert_eq!(names[24], "shapes__1__Empty");
+    assert_eq!(
+        &names[25..],
+        &["status__Active", "status__Suspended", "status__Deleted"]
+    );
+}
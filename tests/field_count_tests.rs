@@ -0,0 +1,120 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(StructReflection)]
+struct Shape {
+    name: String,
+    corners: [Point; 3],
+    offset: (i32, (u8, u8)),
+    origin: Option<Point>,
+    #[reflect(option = "collapse")]
+    scale: Option<Point>,
+    unit: (),
+    tags: Vec<String>,
+    #[reflect(skip)]
+    cache: HashMap<String, f64>,
+}
+
+#[derive(StructReflection)]
+struct Wrapper<T, const N: usize> {
+    value: T,
+    samples: [Point; N],
+    #[reflect(flatten)]
+    point: Box<Point>,
+}
+
+#[derive(StructReflection)]
+enum Prefixed {
+    Circle { center: Point, radius: f64 },
+    Segment(Point, Point),
+    Empty,
+}
+
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "variants")]
+enum Status {
+    Active,
+    Deleted(u64),
+}
+
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "union")]
+enum Event {
+    Click { x: i32, y: i32 },
+    Scroll { y: i32, delta: f32 },
+    Close,
+}
+
+// Derives, but has no `FIELD_COUNT`: `value` holds a different number of names
+// depending on the variant
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "union")]
+enum Reading {
+    Scalar { value: f64 },
+    Vector { value: Point },
+}
+
+// One slot per reflected column, sized at compile time
+struct Columns<const N: usize> {
+    values: [f64; N],
+}
+
+#[test]
+fn test_field_count_matches_names() {
+    assert_eq!(Point::FIELD_COUNT, 2);
+    assert_eq!(Shape::FIELD_COUNT, 14);
+    assert_eq!(
+        Shape::FIELD_COUNT,
+        Shape::struct_reflection().unwrap().len()
+    );
+    assert_eq!(
+        Wrapper::<u8, 2>::FIELD_COUNT,
+        Wrapper::<u8, 2>::struct_reflection().unwrap().len()
+    );
+    assert_eq!(Wrapper::<Point, 0>::FIELD_COUNT, 3);
+}
+
+#[test]
+fn test_enum_field_count() {
+    assert_eq!(
+        Prefixed::FIELD_COUNT,
+        Prefixed::struct_reflection().unwrap().len()
+    );
+    assert_eq!(Status::FIELD_COUNT, 2);
+    assert_eq!(
+        Event::FIELD_COUNT,
+        Event::struct_reflection().unwrap().len()
+    );
+}
+
+#[test]
+fn test_field_count_in_const_context() {
+    let row = [0.0; Point::FIELD_COUNT];
+    assert_eq!(row.len(), 2);
+
+    let columns = Columns::<{ Shape::FIELD_COUNT }> {
+        values: [1.0; Shape::FIELD_COUNT],
+    };
+    assert_eq!(columns.values.len(), 14);
+
+    const TOTAL: usize = Point::FIELD_COUNT + Wrapper::<u8, 4>::FIELD_COUNT;
+    assert_eq!(TOTAL, 13);
+}
+
+#[test]
+fn test_field_count_with_glob_import() {
+    // Everything the crate exports is in scope, hidden items included
+    use struct_reflection::*;
+
+    assert_eq!(Point::FIELD_COUNT, 2);
+    assert_eq!(<Box<Point>>::FIELD_COUNT, 2);
+}
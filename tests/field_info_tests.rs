@@ -0,0 +1,79 @@
+#![allow(dead_code)]
+
+use struct_reflection::{FieldInfo, PrimitiveKind, StructReflection};
+
+#[derive(StructReflection)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(StructReflection)]
+struct Record<T> {
+    id: u64,
+    name: String,
+    active: bool,
+    initial: char,
+    value: T,
+    position: Point,
+    scores: [i16; 2],
+    label: Option<String>,
+    #[reflect(option = "collapse")]
+    origin: Option<Point>,
+}
+
+fn info(fields: &[FieldInfo], name: &str) -> FieldInfo {
+    fields
+        .iter()
+        .find(|field| field.path.join("__") == name)
+        .unwrap_or_else(|| panic!("no field named {name}"))
+        .clone()
+}
+
+#[test]
+fn test_primitive_kinds() {
+    let fields = Record::<u8>::struct_reflection_fields().unwrap();
+
+    assert_eq!(info(&fields, "id").kind, PrimitiveKind::Int);
+    assert_eq!(info(&fields, "id").type_name, "u64");
+    assert_eq!(info(&fields, "name").kind, PrimitiveKind::String);
+    assert_eq!(info(&fields, "active").kind, PrimitiveKind::Bool);
+    assert_eq!(info(&fields, "initial").kind, PrimitiveKind::Char);
+    assert_eq!(info(&fields, "position__x").kind, PrimitiveKind::Float);
+    assert_eq!(info(&fields, "scores__1").type_name, "i16");
+    assert_eq!(info(&fields, "label__optional").kind, PrimitiveKind::String);
+}
+
+#[test]
+fn test_generic_fields() {
+    let fields = Record::<f32>::struct_reflection_fields().unwrap();
+    let value = info(&fields, "value");
+    assert!(value.is_generic);
+    assert_eq!(value.type_name, "f32");
+    assert_eq!(value.kind, PrimitiveKind::Float);
+    assert!(!info(&fields, "id").is_generic);
+
+    let fields = Record::<Vec<u8>>::struct_reflection_fields().unwrap();
+    let value = info(&fields, "value");
+    assert!(value.is_generic);
+    assert_eq!(value.kind, PrimitiveKind::Other);
+}
+
+#[test]
+fn test_collapsed_option() {
+    let fields = Record::<u8>::struct_reflection_fields().unwrap();
+    let origin = info(&fields, "origin__optional");
+    assert_eq!(origin.kind, PrimitiveKind::Other);
+    // The collapsed leaf holds the option's value, and reports its type
+    assert_eq!(origin.type_name, "field_info_tests::Point");
+}
+
+#[test]
+fn test_fields_match_names() {
+    let names: Vec<String> = Record::<u8>::struct_reflection_fields()
+        .unwrap()
+        .iter()
+        .map(|field| field.path.join("__"))
+        .collect();
+    assert_eq!(names, Record::<u8>::struct_reflection().unwrap());
+}
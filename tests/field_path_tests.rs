@@ -0,0 +1,133 @@
+#![allow(dead_code)]
+
+use struct_reflection::{FieldPath, Segment, StructReflection};
+
+#[derive(StructReflection)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(StructReflection)]
+struct Shape {
+    #[reflect(rename = "a__b")]
+    literal: u8,
+    a: Point,
+    corners: [Point; 2],
+    pair: (u8, [bool; 2]),
+    center: Option<Point>,
+}
+
+#[derive(StructReflection)]
+struct Wrapper(u8, Point);
+
+fn named(name: &str) -> Segment {
+    Segment::Named(name.to_string())
+}
+
+#[test]
+fn test_typed_segments() {
+    let paths = Shape::struct_reflection_paths().unwrap();
+    assert_eq!(paths.len(), 12);
+    assert_eq!(paths[0].segments(), &[named("a__b")]);
+    assert_eq!(paths[1].segments(), &[named("a"), named("x")]);
+    assert_eq!(
+        paths[5].segments(),
+        &[named("corners"), Segment::ArrayIndex(1), named("x")]
+    );
+    assert_eq!(
+        paths[9].segments(),
+        &[
+            named("pair"),
+            Segment::TupleIndex(1),
+            Segment::ArrayIndex(1)
+        ]
+    );
+    assert_eq!(
+        paths[11].segments(),
+        &[named("center"), named("y"), Segment::OptionalMarker]
+    );
+}
+
+#[test]
+fn test_tuple_struct_segments() {
+    let paths = Wrapper::struct_reflection_paths().unwrap();
+    assert_eq!(
+        paths,
+        vec![
+            FieldPath::from(Segment::TupleIndex(0)),
+            FieldPath::from(vec![Segment::TupleIndex(1), named("x")]),
+            FieldPath::from(vec![Segment::TupleIndex(1), named("y")]),
+        ]
+    );
+}
+
+#[test]
+fn test_display() {
+    let paths: Vec<String> = Shape::struct_reflection_paths()
+        .unwrap()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            "a__b",
+            "a.x",
+            "a.y",
+            "corners[0].x",
+            "corners[0].y",
+            "corners[1].x",
+            "corners[1].y",
+            "pair.0",
+            "pair.1[0]",
+            "pair.1[1]",
+            "center.x?",
+            "center.y?"
+        ]
+    );
+}
+
+#[test]
+fn test_join_matches_struct_reflection() {
+    let joined: Vec<String> = Shape::struct_reflection_paths()
+        .unwrap()
+        .iter()
+        .map(|path| path.join("__"))
+        .collect();
+    assert_eq!(joined, Shape::struct_reflection().unwrap());
+    assert_eq!(joined[0], "a__b");
+    assert_eq!(joined[1], "a__x");
+}
+
+#[test]
+fn test_parse_round_trip() {
+    for path in Shape::struct_reflection_paths().unwrap() {
+        let parsed: FieldPath = path.to_string().parse().unwrap();
+        assert_eq!(parsed, path);
+    }
+
+    let quoted = FieldPath::from(vec![
+        named("e-mail"),
+        named("a.b"),
+        named("say \"hi\""),
+        named("42"),
+    ]);
+    assert_eq!(quoted.to_string(), r#"e-mail."a.b"."say \"hi\""."42""#);
+    assert_eq!(quoted.to_string().parse::<FieldPath>().unwrap(), quoted);
+}
+
+#[test]
+fn test_parse_errors() {
+    for invalid in [
+        "a..b", ".a", "a[", "a[x]", "a]", "a.\"b", "a b", "a.", "a?b",
+    ] {
+        assert!(
+            invalid.parse::<FieldPath>().is_err(),
+            "{invalid:?} should not parse"
+        );
+    }
+
+    let err = "items[x]".parse::<FieldPath>().unwrap_err();
+    assert_eq!(err.position(), 6);
+}
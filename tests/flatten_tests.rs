@@ -0,0 +1,231 @@
+#![allow(dead_code)]
+
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+struct Timestamps {
+    created_at: u64,
+    updated_at: u64,
+}
+
+#[derive(StructReflection)]
+struct Audit {
+    author: String,
+    #[reflect(flatten)]
+    timestamps: Timestamps,
+}
+
+#[derive(StructReflection)]
+struct Article {
+    id: u32,
+    #[reflect(flatten)]
+    audit: Audit,
+    title: String,
+    #[reflect(flatten)]
+    published: Option<Timestamps>,
+}
+
+#[derive(StructReflection)]
+#[reflect(rename_all = "camelCase")]
+struct Comment {
+    #[reflect(flatten)]
+    timestamps: Timestamps,
+    body_text: String,
+}
+
+#[derive(StructReflection)]
+struct Colliding {
+    created_at: u64,
+    #[reflect(flatten)]
+    timestamps: Timestamps,
+}
+
+#[derive(StructReflection)]
+enum Event {
+    Created {
+        #[reflect(flatten)]
+        timestamps: Timestamps,
+    },
+    Deleted(u32),
+}
+
+#[test]
+fn test_flattened_fields() {
+    assert_eq!(
+        Article::struct_reflection().unwrap(),
+        vec![
+            "id",
+            "author",
+            "created_at",
+            "updated_at",
+            "title",
+            "created_at__optional",
+            "updated_at__optional",
+        ]
+    );
+}
+
+#[test]
+fn test_flatten_keeps_nested_names() {
+    // `rename_all` applies to the parent's own fields only
+    assert_eq!(
+        Comment::struct_reflection().unwrap(),
+        vec!["created_at", "updated_at", "bodyText"]
+    );
+    assert_eq!(
+        Event::struct_reflection().unwrap(),
+        vec!["Created__created_at", "Created__updated_at", "Deleted__0"]
+    );
+}
+
+#[test]
+#[should_panic(expected = "`Colliding` reflects `created_at` more than once")]
+fn test_collision_panics() {
+    Colliding::struct_reflection();
+}
+
+#[test]
+fn test_flattened_instance() {
+    let article = Article {
+        id: 1,
+        audit: Audit {
+            author: "ann".to_string(),
+            timestamps: Timestamps {
+                created_at: 0,
+                updated_at: 0,
+            },
+        },
+        title: "Flatten".to_string(),
+        published: None,
+    };
+    assert_eq!(
+        article.struct_reflection_instance().unwrap(),
+        Article::struct_reflection().unwrap()
+    );
+}
+
+#[derive(StructReflection)]
+struct FlattenedLeaves<T> {
+    id: u8,
+    #[reflect(flatten)]
+    tags: Vec<String>,
+    #[reflect(flatten)]
+    a: u8,
+    #[reflect(flatten)]
+    b: u16,
+    #[reflect(flatten)]
+    code: T,
+}
+
+#[test]
+fn test_flattened_leaves_keep_their_name() {
+    // A leaf has no names of its own to inline
+    assert_eq!(
+        FlattenedLeaves::<char>::struct_reflection().unwrap(),
+        vec!["id", "tags", "a", "b", "code"]
+    );
+
+    let leaves = FlattenedLeaves {
+        id: 1,
+        tags: vec!["x".to_string()],
+        a: 2,
+        b: 3,
+        code: 'c',
+    };
+    assert_eq!(
+        leaves.struct_reflection_instance().unwrap(),
+        FlattenedLeaves::<char>::struct_reflection().unwrap()
+    );
+    assert_eq!(leaves.struct_reflection_get_as::<u16>("b"), Some(&3));
+
+    let copy = FlattenedLeaves::<char>::from_row(&leaves.to_row());
+    assert!(copy.is_err(), "`tags` cannot be rebuilt from a row");
+    let error = copy.err().unwrap();
+    assert_eq!(error.mistyped.len(), 1);
+    assert_eq!(error.mistyped[0].column, "tags");
+    assert!(error.missing.is_empty());
+}
+
+#[derive(StructReflection)]
+struct FlattenedScalars {
+    #[reflect(flatten)]
+    a: u8,
+    #[reflect(flatten)]
+    b: u16,
+}
+
+#[test]
+fn test_flattened_leaves_do_not_collide() {
+    assert_eq!(
+        FlattenedScalars::struct_reflection().unwrap(),
+        vec!["a", "b"]
+    );
+
+    let scalars = FlattenedScalars { a: 1, b: 2 };
+    let copy = FlattenedScalars::from_row(&scalars.to_row()).unwrap();
+    assert_eq!((copy.a, copy.b), (1, 2));
+}
+
+#[derive(StructReflection)]
+struct Meters(f64);
+
+// Named by its prefix only
+#[derive(StructReflection)]
+struct Span(#[reflect(prefix = "at")] (u8, u8));
+
+#[derive(StructReflection)]
+struct FlattenedUnnamed {
+    #[reflect(flatten)]
+    a: (u8, u8),
+    #[reflect(flatten)]
+    b: Option<u16>,
+    #[reflect(flatten)]
+    c: Option<u32>,
+    #[reflect(flatten)]
+    length: Meters,
+    #[reflect(flatten)]
+    stamps: Option<Timestamps>,
+    #[reflect(flatten)]
+    span: Span,
+}
+
+#[test]
+fn test_flattened_fields_without_names_keep_theirs() {
+    assert_eq!(
+        FlattenedUnnamed::struct_reflection().unwrap(),
+        vec![
+            "a__0",
+            "a__1",
+            "b__optional",
+            "c__optional",
+            "length__0",
+            "created_at__optional",
+            "updated_at__optional",
+            "at__0",
+            "at__1",
+        ]
+    );
+
+    let unnamed = FlattenedUnnamed {
+        a: (1, 2),
+        b: Some(3),
+        c: None,
+        length: Meters(4.5),
+        stamps: Some(Timestamps {
+            created_at: 5,
+            updated_at: 6,
+        }),
+        span: Span((7, 8)),
+    };
+    let copy = FlattenedUnnamed::from_row(&unnamed.to_row()).unwrap();
+    assert_eq!(
+        (copy.a, copy.b, copy.c, copy.length.0),
+        ((1, 2), Some(3), None, 4.5)
+    );
+    assert_eq!(copy.stamps.map(|stamps| stamps.updated_at), Some(6));
+    assert_eq!(copy.span.0, (7, 8));
+    assert_eq!(
+        unnamed.struct_reflection_get_as::<u16>("b__optional"),
+        Some(&3)
+    );
+}
@@ -0,0 +1,153 @@
+#![allow(dead_code)]
+
+use std::net::IpAddr;
+use std::num::{NonZeroU32, Wrapping};
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, SystemTime};
+
+use struct_reflection::{PrimitiveKind, StructReflection};
+
+type Id = u64;
+type Name = String;
+
+#[derive(StructReflection)]
+struct Record {
+    id: Id,
+    name: Name,
+    count: std::primitive::u64,
+    limit: NonZeroU32,
+    timeout: Duration,
+    created: SystemTime,
+    home: PathBuf,
+    address: IpAddr,
+    checksum: Wrapping<i32>,
+    ready: AtomicBool,
+    ids: [Id; 2],
+    maybe_id: Option<Id>,
+    pair: (Name, Box<str>),
+}
+
+#[test]
+fn test_std_leaves() {
+    assert_eq!(
+        Record::struct_reflection().unwrap(),
+        vec![
+            "id",
+            "name",
+            "count",
+            "limit",
+            "timeout",
+            "created",
+            "home",
+            "address",
+            "checksum",
+            "ready",
+            "ids__0",
+            "ids__1",
+            "maybe_id__optional",
+            "pair__0",
+            "pair__1",
+        ]
+    );
+}
+
+#[test]
+fn test_kinds_come_from_the_type() {
+    let kinds: Vec<PrimitiveKind> = Record::struct_reflection_fields()
+        .unwrap()
+        .iter()
+        .map(|field| field.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            PrimitiveKind::Int,
+            PrimitiveKind::String,
+            PrimitiveKind::Int,
+            PrimitiveKind::Int,
+            PrimitiveKind::Other,
+            PrimitiveKind::Other,
+            PrimitiveKind::Other,
+            PrimitiveKind::Other,
+            PrimitiveKind::Int,
+            PrimitiveKind::Bool,
+            PrimitiveKind::Int,
+            PrimitiveKind::Int,
+            PrimitiveKind::Int,
+            PrimitiveKind::String,
+            PrimitiveKind::String,
+        ]
+    );
+
+    let fields = Record::struct_reflection_fields().unwrap();
+    assert_eq!(fields[0].type_name, "u64");
+    assert_eq!(fields[4].type_name, "core::time::Duration");
+}
+
+#[derive(StructReflection)]
+struct Money {
+    cents: i64,
+    currency: String,
+}
+
+// Not reflectable at all
+struct Opaque;
+
+#[derive(StructReflection)]
+struct Invoice {
+    subtotal: Money,
+    #[reflect(leaf)]
+    total: Money,
+    #[reflect(leaf)]
+    lines: [Money; 2],
+    #[reflect(leaf)]
+    range: (Money, Option<Money>),
+    #[reflect(leaf)]
+    handle: Opaque,
+    #[reflect(leaf)]
+    label: &'static str,
+}
+
+#[test]
+fn test_forced_leaves() {
+    assert_eq!(
+        Invoice::struct_reflection().unwrap(),
+        vec![
+            "subtotal__cents",
+            "subtotal__currency",
+            "total",
+            "lines__0",
+            "lines__1",
+            "range__0",
+            "range__1__optional",
+            "handle",
+            "label",
+        ]
+    );
+
+    let fields = Invoice::struct_reflection_fields().unwrap();
+    assert_eq!(fields[2].type_name, std::any::type_name::<Money>());
+    assert_eq!(fields[2].kind, PrimitiveKind::Other);
+    assert_eq!(fields[8].type_name, "&str");
+}
+
+#[test]
+fn test_forced_leaf_instance() {
+    let money = || Money {
+        cents: 100,
+        currency: "EUR".to_string(),
+    };
+    let invoice = Invoice {
+        subtotal: money(),
+        total: money(),
+        lines: [money(), money()],
+        range: (money(), Some(money())),
+        handle: Opaque,
+        label: "invoice",
+    };
+    assert_eq!(
+        invoice.struct_reflection_instance().unwrap(),
+        Invoice::struct_reflection().unwrap()
+    );
+}
@@ -0,0 +1,173 @@
+#![allow(dead_code)]
+
+use std::collections::{BTreeMap, HashMap};
+
+use struct_reflection::{FieldPath, Segment, StructReflection};
+
+#[derive(StructReflection)]
+struct Limit {
+    soft: u32,
+    hard: u32,
+}
+
+#[derive(StructReflection)]
+struct Config {
+    name: String,
+    env: HashMap<String, String>,
+    #[reflect(values)]
+    limits: BTreeMap<String, u64>,
+    #[reflect(values)]
+    quotas: BTreeMap<String, Limit>,
+}
+
+#[derive(StructReflection)]
+struct Telemetry {
+    #[reflect(elements)]
+    samples: Vec<(u8, u8)>,
+    #[reflect(values)]
+    counters: Option<BTreeMap<u16, bool>>,
+    last: Option<Limit>,
+}
+
+#[derive(StructReflection)]
+enum Source {
+    Static(u8),
+    Dynamic {
+        #[reflect(values)]
+        settings: BTreeMap<String, u8>,
+    },
+}
+
+fn config() -> Config {
+    Config {
+        name: "default".to_string(),
+        env: HashMap::from([("HOME".to_string(), "/root".to_string())]),
+        limits: BTreeMap::from([("mem".to_string(), 512), ("cpu".to_string(), 2)]),
+        quotas: BTreeMap::from([("disk".to_string(), Limit { soft: 10, hard: 20 })]),
+    }
+}
+
+#[test]
+fn test_map_type_names() {
+    assert_eq!(
+        Config::struct_reflection().unwrap(),
+        vec![
+            "name",
+            "env",
+            "limits__{}",
+            "quotas__{}__soft",
+            "quotas__{}__hard"
+        ]
+    );
+}
+
+#[test]
+fn test_instance_keys() {
+    assert_eq!(
+        config().struct_reflection_instance().unwrap(),
+        vec![
+            "name",
+            "env",
+            "limits__cpu",
+            "limits__mem",
+            "quotas__disk__soft",
+            "quotas__disk__hard"
+        ]
+    );
+
+    let paths: Vec<FieldPath> = config()
+        .struct_reflection_instance_fields()
+        .unwrap()
+        .into_iter()
+        .map(|field| field.path)
+        .collect();
+    assert_eq!(
+        paths[2].segments(),
+        &[
+            Segment::Named("limits".to_string()),
+            Segment::Key("cpu".to_string())
+        ]
+    );
+    assert_eq!(paths[2].to_string(), r#"limits["cpu"]"#);
+    assert_eq!(r#"limits["cpu"]"#.parse::<FieldPath>().unwrap(), paths[2]);
+    assert_eq!(
+        "limits{}".parse::<FieldPath>().unwrap().segments()[1],
+        Segment::AnyKey
+    );
+}
+
+#[test]
+fn test_instance_elements_and_options() {
+    let telemetry = Telemetry {
+        samples: vec![(1, 2), (3, 4)],
+        counters: Some(BTreeMap::from([(7, true)])),
+        last: None,
+    };
+    assert_eq!(
+        telemetry.struct_reflection_instance().unwrap(),
+        vec![
+            "samples__0__0",
+            "samples__0__1",
+            "samples__1__0",
+            "samples__1__1",
+            "counters__7__optional",
+            "last__soft__optional",
+            "last__hard__optional",
+        ]
+    );
+
+    let empty = Telemetry {
+        samples: Vec::new(),
+        counters: None,
+        last: None,
+    };
+    assert_eq!(
+        empty.struct_reflection_instance().unwrap(),
+        vec![
+            "counters__{}__optional",
+            "last__soft__optional",
+            "last__hard__optional"
+        ]
+    );
+}
+
+#[test]
+fn test_instance_enum() {
+    let source = Source::Dynamic {
+        settings: BTreeMap::from([("level".to_string(), 3)]),
+    };
+    assert_eq!(
+        source.struct_reflection_instance().unwrap(),
+        vec!["Static__0", "Dynamic__settings__level"]
+    );
+    assert_eq!(
+        Source::Static(1).struct_reflection_instance().unwrap(),
+        vec!["Static__0", "Dynamic__settings__{}"]
+    );
+}
+
+#[cfg(feature = "indexmap")]
+#[test]
+fn test_index_map() {
+    use indexmap::IndexMap;
+
+    #[derive(StructReflection)]
+    struct Ordered {
+        plain: IndexMap<String, u8>,
+        #[reflect(values)]
+        keyed: IndexMap<String, u8>,
+    }
+
+    let ordered = Ordered {
+        plain: IndexMap::new(),
+        keyed: IndexMap::from([("z".to_string(), 1), ("a".to_string(), 2)]),
+    };
+    assert_eq!(
+        Ordered::struct_reflection().unwrap(),
+        vec!["plain", "keyed__{}"]
+    );
+    assert_eq!(
+        ordered.struct_reflection_instance().unwrap(),
+        vec!["plain", "keyed__z", "keyed__a"]
+    );
+}
@@ -2,7 +2,6 @@
 use std::marker::PhantomData;
 
 use struct_reflection::StructReflection;
-use struct_reflection::StructReflectionHelper;
 
 // Simple struct without generics
 #[derive(StructReflection)]
@@ -348,7 +347,15 @@ fn test_optional_fields_struct() {
 #[test]
 fn test_nested_optional_struct() {
     let names = NestedOptionalStruct::struct_reflection();
-    assert_eq!(names.unwrap(), vec!["id", "maybe_basic__optional"]);
+    assert_eq!(
+        names.unwrap(),
+        vec![
+            "id",
+            "maybe_basic__field_one__optional",
+            "maybe_basic__field_two__optional",
+            "maybe_basic__field_three__optional"
+        ]
+    );
 }
 
 #[test]
@@ -356,7 +363,12 @@ fn test_optional_generic_struct() {
     let names = OptionalGenericStruct::<i32>::struct_reflection();
     assert_eq!(
         names.unwrap(),
-        vec!["id", "maybe_data__optional", "maybe_array__optional"]
+        vec![
+            "id",
+            "maybe_data__optional",
+            "maybe_array__0__optional",
+            "maybe_array__1__optional"
+        ]
     );
 }
 
@@ -411,3 +423,19 @@ fn test_nested_array_of_generic_structs() {
         ]
     );
 }
+
+fn reflect_via_bound<T: StructReflection>() -> Vec<String> {
+    T::struct_reflection().unwrap()
+}
+
+#[test]
+fn test_derived_struct_satisfies_trait_bound() {
+    assert_eq!(
+        reflect_via_bound::<BasicStruct>(),
+        vec!["field_one", "field_two", "field_three"]
+    );
+    assert_eq!(
+        reflect_via_bound::<GenericStruct<String>>(),
+        vec!["data", "count", "enabled"]
+    );
+}
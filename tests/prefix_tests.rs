@@ -0,0 +1,55 @@
+#![allow(dead_code)]
+
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+struct Address {
+    street: String,
+    city: String,
+}
+
+#[derive(StructReflection)]
+struct Order {
+    #[reflect(prefix = "bill")]
+    billing_address: Address,
+    #[reflect(prefix = "ship", rename = "shipping")]
+    shipping_address: Option<Address>,
+    #[reflect(prefix = "qty")]
+    quantity: u32,
+    #[reflect(prefix = "note")]
+    comment: Option<String>,
+    #[reflect(prefix = "dim")]
+    dimensions: [u16; 2],
+}
+
+#[test]
+fn test_prefix_replaces_nested_prefix() {
+    assert_eq!(
+        Order::struct_reflection().unwrap(),
+        vec![
+            "bill__street",
+            "bill__city",
+            "ship__street__optional",
+            "ship__city__optional",
+            "quantity",
+            "comment__optional",
+            "dim__0",
+            "dim__1",
+        ]
+    );
+}
+
+#[derive(StructReflection)]
+#[reflect(rename_all = "camelCase", option = "prefix")]
+struct Invoice {
+    #[reflect(prefix = "billTo")]
+    billing_address: Option<Address>,
+}
+
+#[test]
+fn test_prefix_with_container_attributes() {
+    assert_eq!(
+        Invoice::struct_reflection().unwrap(),
+        vec!["billTo__optional__street", "billTo__optional__city"]
+    );
+}
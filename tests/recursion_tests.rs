@@ -0,0 +1,156 @@
+#![allow(dead_code)]
+
+use struct_reflection::{FieldPath, Segment, StructReflection};
+
+#[derive(StructReflection)]
+struct Node {
+    value: i32,
+    next: Option<Box<Node>>,
+}
+
+#[derive(StructReflection)]
+struct Tree {
+    label: String,
+    #[reflect(elements)]
+    children: Vec<Tree>,
+}
+
+#[derive(StructReflection)]
+struct Department {
+    name: String,
+    head: Employee,
+}
+
+#[derive(StructReflection)]
+struct Employee {
+    id: u32,
+    department: Option<Box<Department>>,
+}
+
+#[derive(StructReflection)]
+struct Company {
+    main: Department,
+    list: Node,
+}
+
+#[test]
+fn test_self_reference() {
+    assert_eq!(
+        Node::struct_reflection().unwrap(),
+        vec!["value", "next__$ref:Node__optional"]
+    );
+
+    let paths = Node::struct_reflection_paths().unwrap();
+    assert_eq!(
+        paths[1].segments(),
+        &[
+            Segment::Named("next".to_string()),
+            Segment::Ref("Node".to_string()),
+            Segment::OptionalMarker
+        ]
+    );
+    assert_eq!(paths[1].to_string(), "next.$ref:Node?");
+    assert_eq!("next.$ref:Node?".parse::<FieldPath>().unwrap(), paths[1]);
+}
+
+#[test]
+fn test_recursive_collections() {
+    assert_eq!(
+        Tree::struct_reflection().unwrap(),
+        vec!["label", "children__[]__$ref:Tree"]
+    );
+}
+
+#[test]
+fn test_mutual_recursion() {
+    assert_eq!(
+        Department::struct_reflection().unwrap(),
+        vec![
+            "name",
+            "head__id",
+            "head__department__$ref:Department__optional",
+        ]
+    );
+}
+
+#[test]
+fn test_instance_of_recursive_type() {
+    let list = Node {
+        value: 1,
+        next: Some(Box::new(Node {
+            value: 2,
+            next: None,
+        })),
+    };
+    assert_eq!(
+        list.struct_reflection_instance().unwrap(),
+        vec![
+            "value",
+            "next__value__optional",
+            "next__next__$ref:Node__optional__optional"
+        ]
+    );
+}
+
+#[test]
+fn test_type_graph() {
+    let graph = Company::struct_reflection_graph();
+    let names: Vec<&str> = graph.nodes().iter().map(|node| node.name).collect();
+    assert_eq!(names, vec!["Company", "Department", "Employee", "Node"]);
+    assert_eq!(graph.edges(), &[(0, 1), (1, 2), (2, 1), (0, 3), (3, 3)]);
+    assert_eq!(graph.cycles(), &[vec![1, 2], vec![3]]);
+    assert!(graph.is_recursive());
+
+    let graph = Department::struct_reflection_graph();
+    assert_eq!(graph.cycles(), &[vec![0, 1]]);
+    assert_eq!(graph.nodes()[0].name, "Department");
+
+    assert!(!Tree::struct_reflection_graph().cycles().is_empty());
+}
+
+#[derive(StructReflection)]
+struct Flat {
+    a: u8,
+    b: (u16, u32),
+}
+
+#[test]
+fn test_non_recursive_graph() {
+    let graph = Flat::struct_reflection_graph();
+    assert_eq!(graph.nodes().len(), 1);
+    assert!(graph.edges().is_empty());
+    assert!(!graph.is_recursive());
+}
+
+#[derive(StructReflection)]
+struct PlainTree {
+    v: i32,
+    children: Vec<PlainTree>,
+}
+
+#[derive(StructReflection)]
+struct Registry {
+    #[reflect(leaf)]
+    hidden: Vec<Node>,
+    parents: std::collections::BTreeMap<String, Option<Box<Registry>>>,
+    handles: Vec<std::fs::File>,
+}
+
+#[test]
+fn test_type_graph_looks_through_leaf_collections() {
+    // The collection is a leaf of the names, but still an edge of the graph
+    assert_eq!(
+        PlainTree::struct_reflection().unwrap(),
+        vec!["v", "children"]
+    );
+    let graph = PlainTree::struct_reflection_graph();
+    assert_eq!(graph.edges(), &[(0, 0)]);
+    assert_eq!(graph.cycles(), &[vec![0]]);
+    assert!(graph.is_recursive());
+
+    // Map values too, but not `leaf` fields or types that are not reflected
+    let graph = Registry::struct_reflection_graph();
+    assert_eq!(graph.nodes().len(), 1);
+    assert_eq!(graph.edges(), &[(0, 0)]);
+    assert!(graph.is_recursive());
+}
@@ -0,0 +1,511 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use struct_reflection::{StructReflection, Value};
+
+#[derive(StructReflection)]
+struct Address {
+    street: String,
+    number: u32,
+}
+
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "union")]
+enum Event {
+    Click { x: i32, y: i32 },
+    Scroll { y: i32, delta: f32 },
+}
+
+#[derive(StructReflection)]
+enum Shape {
+    Circle { radius: f64 },
+    Empty,
+}
+
+type Maybe<T> = Option<T>;
+
+#[derive(StructReflection)]
+struct Record {
+    id: u64,
+    offset: i8,
+    ratio: f32,
+    active: bool,
+    grade: char,
+    name: Box<str>,
+    address: Address,
+    pair: (u8, Rc<str>),
+    nickname: Option<String>,
+    manager: Option<Address>,
+    #[reflect(option = "collapse")]
+    home: Option<Address>,
+    alias: Maybe<String>,
+    tags: Vec<String>,
+    #[reflect(values)]
+    scores: HashMap<String, f32>,
+    event: Event,
+    shape: Shape,
+}
+
+fn record() -> Record {
+    Record {
+        id: 7,
+        offset: -3,
+        ratio: 0.5,
+        active: true,
+        grade: 'A',
+        name: "bob".into(),
+        address: Address {
+            street: "Main St".to_string(),
+            number: 12,
+        },
+        pair: (1, Rc::from("one")),
+        nickname: None,
+        manager: Some(Address {
+            street: "High St".to_string(),
+            number: 3,
+        }),
+        home: None,
+        alias: None,
+        tags: vec!["admin".to_string()],
+        scores: HashMap::from([("math".to_string(), 9.5)]),
+        event: Event::Scroll { y: 4, delta: 0.25 },
+        shape: Shape::Empty,
+    }
+}
+
+#[test]
+fn test_row_matches_names() {
+    let record = record();
+    let names = Record::struct_reflection().unwrap();
+    let row = record.to_row();
+    assert_eq!(row.len(), names.len());
+
+    let columns: HashMap<&str, &Value> = names.iter().map(String::as_str).zip(&row).collect();
+
+    assert_eq!(columns["id"], &Value::UInt(7));
+    assert_eq!(columns["offset"], &Value::Int(-3));
+    assert_eq!(columns["ratio"], &Value::Float(0.5));
+    assert_eq!(columns["active"], &Value::Bool(true));
+    assert_eq!(columns["grade"], &Value::Char('A'));
+    assert_eq!(columns["name"], &Value::String("bob".to_string()));
+    assert_eq!(
+        columns["address__street"],
+        &Value::String("Main St".to_string())
+    );
+    assert_eq!(columns["pair__0"], &Value::UInt(1));
+    assert_eq!(columns["pair__1"], &Value::String("one".to_string()));
+    assert_eq!(columns["manager__number__optional"], &Value::UInt(3));
+    assert_eq!(
+        columns["tags"],
+        &Value::Opaque(std::any::type_name::<Vec<String>>())
+    );
+
+    // Unions name the current variant, and prefixed enums hold the enum
+    // itself under the name of a variant without fields
+    assert_eq!(
+        columns["event__variant"],
+        &Value::String("Scroll".to_string())
+    );
+    assert_eq!(columns["event__delta"], &Value::Float(0.25));
+    assert_eq!(columns["event__x"], &Value::Null);
+    assert_eq!(columns["shape__Circle__radius"], &Value::Null);
+    assert!(matches!(columns["shape__Empty"], Value::Opaque(_)));
+}
+
+#[test]
+fn test_row_nulls() {
+    let record = record();
+    let names = Record::struct_reflection().unwrap();
+    let row = record.to_row();
+
+    let nulls: Vec<&str> = names
+        .iter()
+        .zip(&row)
+        .filter(|(_, value)| value.is_null())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(
+        nulls,
+        [
+            "nickname__optional",
+            "home__optional",
+            "alias__optional",
+            "scores__{}",
+            "event__x",
+            "shape__Circle__radius",
+        ]
+    );
+}
+
+#[test]
+fn test_value_display() {
+    let row = Address {
+        street: "Main St".to_string(),
+        number: 12,
+    }
+    .to_row();
+    let line: Vec<String> = row.iter().map(Value::to_string).collect();
+    assert_eq!(line, ["Main St", "12"]);
+
+    assert_eq!(Value::Null.to_string(), "");
+    assert_eq!(Value::Float(1.5).to_string(), "1.5");
+    assert_eq!(Value::Opaque("Vec<u8>").to_string(), "<Vec<u8>>");
+}
+
+#[derive(StructReflection, Debug, PartialEq)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(StructReflection, Debug, PartialEq)]
+#[reflect(enum_strategy = "union")]
+enum Motion {
+    Still,
+    Moving { speed: f32, heading: Option<u16> },
+}
+
+#[derive(StructReflection, Debug, PartialEq)]
+enum Marker {
+    Pin(Point),
+    Hidden,
+}
+
+#[derive(StructReflection, Debug, PartialEq)]
+struct Sample<T> {
+    id: std::num::NonZeroU32,
+    label: Rc<str>,
+    origin: Point,
+    path: [Point; 2],
+    span: (i64, Option<bool>),
+    note: Option<String>,
+    target: Option<Point>,
+    #[reflect(option = "prefix")]
+    previous: Option<Point>,
+    motion: Motion,
+    marker: Marker,
+    #[reflect(flatten)]
+    extra: Point,
+    code: T,
+    #[reflect(prefix = "pos")]
+    position: Box<Point>,
+    #[reflect(skip)]
+    cache: Vec<u8>,
+}
+
+fn sample() -> Sample<char> {
+    Sample {
+        id: std::num::NonZeroU32::new(3).unwrap(),
+        label: Rc::from("first"),
+        origin: Point { x: 0.0, y: -1.5 },
+        path: [Point { x: 1.0, y: 2.0 }, Point { x: 3.0, y: 4.0 }],
+        span: (-10, Some(true)),
+        note: None,
+        target: Some(Point { x: 5.0, y: 6.0 }),
+        previous: None,
+        motion: Motion::Moving {
+            speed: 2.5,
+            heading: None,
+        },
+        marker: Marker::Hidden,
+        extra: Point { x: 7.0, y: 8.0 },
+        code: 'z',
+        position: Box::new(Point { x: 9.0, y: 10.0 }),
+        cache: Vec::new(),
+    }
+}
+
+#[derive(StructReflection, Debug, PartialEq)]
+struct Collapsed {
+    a: u8,
+    #[reflect(option = "collapse")]
+    c: Option<u32>,
+    #[reflect(option = "collapse")]
+    name: Option<String>,
+}
+
+#[test]
+fn test_collapsed_round_trip() {
+    let collapsed = Collapsed {
+        a: 1,
+        c: Some(3),
+        name: None,
+    };
+    assert_eq!(
+        collapsed.to_row(),
+        [Value::UInt(1), Value::UInt(3), Value::Null]
+    );
+    assert_eq!(
+        collapsed.struct_reflection_get_as::<u32>("c__optional"),
+        Some(&3)
+    );
+    assert_eq!(Collapsed::from_row(&collapsed.to_row()).unwrap(), collapsed);
+
+    let other = Collapsed {
+        c: None,
+        name: Some("x".to_string()),
+        ..collapsed
+    };
+    assert_eq!(Collapsed::from_row(&other.to_row()).unwrap(), other);
+}
+
+// An option behind an alias is not seen by the derive, and is always collapsed
+#[derive(StructReflection, Debug, PartialEq)]
+struct Aliased {
+    a: u8,
+    c: Maybe<u32>,
+}
+
+#[test]
+fn test_aliased_option_round_trip() {
+    let aliased = Aliased { a: 1, c: Some(2) };
+    assert_eq!(Aliased::struct_reflection().unwrap(), ["a", "c__optional"]);
+    assert_eq!(aliased.to_row(), [Value::UInt(1), Value::UInt(2)]);
+    assert_eq!(
+        aliased.struct_reflection_get_as::<u32>("c__optional"),
+        Some(&2)
+    );
+    let fields = Aliased::struct_reflection_fields().unwrap();
+    assert_eq!(fields[1].type_name, "u32");
+    assert_eq!(Aliased::from_row(&aliased.to_row()).unwrap(), aliased);
+
+    let mut other = Aliased { c: None, ..aliased };
+    assert_eq!(other.to_row(), [Value::UInt(1), Value::Null]);
+    assert_eq!(Aliased::from_row(&other.to_row()).unwrap(), other);
+    other.struct_reflection_set("c__optional", 5u32).unwrap();
+    assert_eq!(other.c, Some(5));
+}
+
+#[derive(StructReflection, Debug, PartialEq)]
+struct Inventory {
+    id: u32,
+    #[reflect(values)]
+    stock: HashMap<String, u32>,
+    #[reflect(elements)]
+    points: Vec<Point>,
+    #[reflect(elements)]
+    history: Option<Vec<u8>>,
+}
+
+#[test]
+fn test_collections_read_empty() {
+    let inventory = Inventory {
+        id: 1,
+        stock: HashMap::from([("bolts".to_string(), 40)]),
+        points: vec![Point { x: 1.0, y: 2.0 }],
+        history: Some(vec![1]),
+    };
+    let row = inventory.to_row();
+    assert_eq!(
+        Inventory::struct_reflection().unwrap(),
+        [
+            "id",
+            "stock__{}",
+            "points__[]__x",
+            "points__[]__y",
+            "history__[]__optional"
+        ]
+    );
+
+    // Only the nulls standing for the contents are read back
+    let copy = Inventory::from_row(&row).unwrap();
+    assert_eq!(copy.id, 1);
+    assert!(copy.stock.is_empty() && copy.points.is_empty());
+    assert_eq!(copy.history, None);
+
+    let mut columns: HashMap<String, Value> = HashMap::from([
+        ("id".to_string(), Value::UInt(2)),
+        ("stock__{}".to_string(), Value::UInt(3)),
+        ("points__[]__x".to_string(), Value::Null),
+    ]);
+    let error = Inventory::from_map(&columns).unwrap_err();
+    assert_eq!(error.missing, ["points__[]__y"]);
+    assert_eq!(error.mistyped[0].column, "stock__{}");
+
+    columns.insert("stock__{}".to_string(), Value::Null);
+    columns.insert("points__[]__y".to_string(), Value::Null);
+    assert!(Inventory::from_map(&columns).is_ok());
+}
+
+mod geo {
+    use struct_reflection::StructReflection;
+
+    #[derive(StructReflection, Debug, PartialEq)]
+    pub struct Path {
+        pub len: u32,
+    }
+}
+
+#[derive(StructReflection, Debug, PartialEq)]
+struct Route {
+    id: u32,
+    path: geo::Path,
+}
+
+#[test]
+fn test_last_field_named_like_an_unsized_type() {
+    let route = Route {
+        id: 1,
+        path: geo::Path { len: 5 },
+    };
+    let columns = HashMap::from([
+        ("id".to_string(), Value::UInt(1)),
+        ("path__len".to_string(), Value::UInt(5)),
+    ]);
+    assert_eq!(Route::from_map(&columns).unwrap(), route);
+    assert_eq!(Route::from_row(&route.to_row()).unwrap(), route);
+}
+
+#[derive(StructReflection, Debug, PartialEq)]
+struct Flat {
+    id: u32,
+    name: String,
+    score: Option<f64>,
+}
+
+#[test]
+fn test_row_round_trip() {
+    let sample = sample();
+    let row = sample.to_row();
+    assert_eq!(Sample::<char>::from_row(&row).unwrap(), sample);
+
+    let names = Sample::<char>::struct_reflection().unwrap();
+    let columns: HashMap<String, Value> = names.into_iter().zip(row).collect();
+    assert_eq!(Sample::<char>::from_map(&columns).unwrap(), sample);
+
+    let other = Sample {
+        note: Some("hi".to_string()),
+        target: None,
+        previous: Some(Point { x: 1.0, y: 1.0 }),
+        motion: Motion::Still,
+        marker: Marker::Pin(Point { x: 2.0, y: 2.0 }),
+        ..sample
+    };
+    assert_eq!(Sample::<char>::from_row(&other.to_row()).unwrap(), other);
+}
+
+#[test]
+fn test_from_map_parses_strings() {
+    // As read from a CSV file, with empty cells for nulls
+    let columns: HashMap<String, Value> = [("id", "7"), ("name", "bob"), ("score__optional", "")]
+        .into_iter()
+        .map(|(name, cell)| {
+            let value = match cell {
+                "" => Value::Null,
+                cell => Value::String(cell.to_string()),
+            };
+            (name.to_string(), value)
+        })
+        .collect();
+
+    assert_eq!(
+        Flat::from_map(&columns).unwrap(),
+        Flat {
+            id: 7,
+            name: "bob".to_string(),
+            score: None,
+        }
+    );
+
+    // Integers fill float fields
+    let row = [
+        Value::UInt(1),
+        Value::String("ann".to_string()),
+        Value::Int(2),
+    ];
+    assert_eq!(Flat::from_row(&row).unwrap().score, Some(2.0));
+}
+
+#[test]
+fn test_from_row_errors() {
+    let columns = HashMap::from([
+        ("id".to_string(), Value::Float(1.5)),
+        ("nme".to_string(), Value::String("bob".to_string())),
+        (
+            "score__optional".to_string(),
+            Value::String("high".to_string()),
+        ),
+    ]);
+    let error = Flat::from_map(&columns).unwrap_err();
+
+    assert_eq!(error.missing, ["name"]);
+    assert_eq!(error.extra, ["nme"]);
+    let mistyped: Vec<(&str, &str)> = error
+        .mistyped
+        .iter()
+        .map(|column| (column.column.as_str(), column.expected))
+        .collect();
+    assert_eq!(mistyped, [("id", "u32"), ("score__optional", "f64")]);
+    assert_eq!(
+        error.to_string(),
+        "invalid row: missing columns `name`; unknown columns `nme`; \
+         column `id` holds Float(1.5), which is not a `u32`; \
+         column `score__optional` holds String(\"high\"), which is not a `f64`"
+    );
+
+    // Values past the names, and nulls in required columns
+    let row = [Value::UInt(1), Value::Null, Value::Null, Value::Bool(true)];
+    let error = Flat::from_row(&row).unwrap_err();
+    assert_eq!(error.extra, ["3"]);
+    assert_eq!(error.mistyped[0].column, "name");
+    assert_eq!(error.mistyped[0].found, Value::Null);
+}
+
+#[test]
+fn test_from_row_unsupported() {
+    // Collections cannot be rebuilt from a single value
+    let record = record();
+    let error = Record::from_row(&record.to_row()).err().unwrap();
+    let mistyped: Vec<&str> = error
+        .mistyped
+        .iter()
+        .map(|column| column.column.as_str())
+        .collect();
+    assert_eq!(mistyped, ["tags"]);
+
+    // Nor can skipped fields without a default value
+    #[derive(StructReflection, Debug)]
+    struct Handle {
+        id: u8,
+        #[reflect(skip)]
+        file: std::fs::File,
+    }
+    let error = Handle::from_row(&[Value::UInt(1)]).unwrap_err();
+    assert!(error.missing.is_empty());
+    assert_eq!(error.no_default, ["file"]);
+    assert_eq!(
+        error.to_string(),
+        "invalid row: no default value for skipped fields `file`"
+    );
+
+    // The variant of a union enum is named by its tag
+    let row = [
+        Value::String("Flying".to_string()),
+        Value::Null,
+        Value::Null,
+    ];
+    let error = Motion::from_row(&row).unwrap_err();
+    assert_eq!(error.mistyped[0].column, "variant");
+}
+
+#[test]
+fn test_recursive_row() {
+    #[derive(StructReflection, Debug, PartialEq)]
+    struct Node {
+        value: u32,
+        next: Option<Box<Node>>,
+    }
+
+    // Only the first level has columns
+    let node = Node {
+        value: 1,
+        next: None,
+    };
+    assert_eq!(Node::from_row(&node.to_row()).unwrap(), node);
+
+    let row = [Value::UInt(1), Value::UInt(2)];
+    let error = Node::from_row(&row).unwrap_err();
+    assert_eq!(error.mistyped[0].column, "next__$ref:Node__optional");
+}
@@ -0,0 +1,101 @@
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(StructReflection)]
+struct Row<'a> {
+    id: u64,
+    label: Cow<'a, str>,
+    position: Point,
+}
+
+#[derive(StructReflection)]
+struct Pair<T> {
+    left: T,
+    right: T,
+}
+
+#[derive(StructReflection)]
+struct Samples<const N: usize> {
+    values: [u8; N],
+}
+
+#[test]
+fn test_static_names() {
+    let names = Row::struct_reflection_names().unwrap();
+    assert_eq!(names, &["id", "label", "position__x", "position__y"]);
+    assert_eq!(names, Row::struct_reflection().unwrap().as_slice());
+
+    // The same slice is handed out every time
+    assert!(std::ptr::eq(names, Row::struct_reflection_names().unwrap()));
+}
+
+#[test]
+fn test_generic_names_are_cached_per_instantiation() {
+    assert_eq!(
+        Pair::<u8>::struct_reflection_names().unwrap(),
+        &["left", "right"]
+    );
+    assert!(std::ptr::eq(
+        Pair::<Point>::struct_reflection_names().unwrap(),
+        Pair::<Point>::struct_reflection_names().unwrap()
+    ));
+    assert!(!std::ptr::eq(
+        Pair::<Point>::struct_reflection_names().unwrap(),
+        Pair::<u8>::struct_reflection_names().unwrap()
+    ));
+
+    assert_eq!(
+        Samples::<2>::struct_reflection_names().unwrap(),
+        &["values__0", "values__1"]
+    );
+    assert_eq!(
+        Samples::<1>::struct_reflection_names().unwrap(),
+        &["values__0"]
+    );
+}
+
+#[test]
+fn test_boxes_have_the_names_of_their_contents() {
+    assert_eq!(
+        Box::<Point>::struct_reflection_names().unwrap(),
+        Point::struct_reflection_names().unwrap()
+    );
+}
+
+#[test]
+fn test_names_are_cached_by_type_not_by_name() {
+    // Types declared in two blocks of one function share their name
+    let (first_name, first) = {
+        #[derive(StructReflection)]
+        struct Local {
+            a: u8,
+        }
+        (
+            std::any::type_name::<Box<Local>>(),
+            Box::<Local>::struct_reflection_names().unwrap(),
+        )
+    };
+    let (second_name, second) = {
+        #[derive(StructReflection)]
+        struct Local {
+            b: u8,
+        }
+        (
+            std::any::type_name::<Box<Local>>(),
+            Box::<Local>::struct_reflection_names().unwrap(),
+        )
+    };
+
+    assert_eq!(first_name, second_name);
+    assert_eq!(first, &["a"]);
+    assert_eq!(second, &["b"]);
+}
@@ -0,0 +1,10 @@
+use struct_reflection::StructReflection;
+
+fn names<T: StructReflection>() -> Option<Vec<String>> {
+    T::struct_reflection()
+}
+
+fn main() {
+    // A box is only reflected around a type that is
+    names::<Box<u32>>();
+}
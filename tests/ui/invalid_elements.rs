@@ -0,0 +1,11 @@
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+struct Invalid {
+    #[reflect(elements)]
+    count: u32,
+    #[reflect(elements)]
+    names: Vec<&'static str>,
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "flattened")]
+enum Shape {
+    Circle { radius: f64 },
+    Square { side: f64 },
+}
+
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "union")]
+struct NotAnEnum {
+    id: u64,
+}
+
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "union")]
+enum TagCollision {
+    Named { variant: String },
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+struct Inner {
+    a: u8,
+}
+
+#[derive(StructReflection)]
+struct Outer {
+    #[reflect(flatten, rename = "inner")]
+    inner: Inner,
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+struct Inner {
+    a: u8,
+}
+
+#[derive(StructReflection)]
+struct Flattened {
+    #[reflect(flatten, prefix = "i")]
+    inner: Inner,
+}
+
+#[derive(StructReflection)]
+struct Empty {
+    #[reflect(prefix = "")]
+    inner: Inner,
+}
+
+#[derive(StructReflection)]
+#[reflect(transparent)]
+struct Wrapper {
+    #[reflect(prefix = "w")]
+    inner: Inner,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+#[reflect(rename_all = "Title Case")]
+struct Config {
+    max_connections: u32,
+}
+
+#[derive(StructReflection)]
+struct Unknown {
+    #[reflect(renamed = "id")]
+    identifier: u64,
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+#[reflect(transparent)]
+struct TwoFields {
+    a: u8,
+    b: u8,
+}
+
+#[derive(StructReflection)]
+#[reflect(transparent)]
+enum NotAStruct {
+    A(u8),
+}
+
+fn main() {}
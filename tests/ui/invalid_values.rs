@@ -0,0 +1,13 @@
+use std::collections::HashMap;
+
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+struct Invalid {
+    #[reflect(values)]
+    names: Vec<String>,
+    #[reflect(values)]
+    lookup: HashMap<String, &'static str>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
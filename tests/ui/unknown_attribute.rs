@@ -0,0 +1,9 @@
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+#[reflect(krate = "struct_reflection")]
+struct Misspelled {
+    id: u64,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use struct_reflection::StructReflection;
+
+#[derive(StructReflection)]
+struct Unsupported {
+    name: &'static str,
+    pointers: [*const u8; 2],
+    callback: (u8, fn(u8) -> u8),
+}
+
+fn main() {}
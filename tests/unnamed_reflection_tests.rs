@@ -2,7 +2,6 @@
 use std::marker::PhantomData;
 
 use struct_reflection::StructReflection;
-use struct_reflection::StructReflectionHelper;
 
 // Simple tuple struct without generics
 #[derive(StructReflection)]
@@ -238,13 +237,19 @@ fn test_optional_fields_tuple() {
 #[test]
 fn test_nested_optional_tuple() {
     let names = NestedOptionalTuple::struct_reflection();
-    assert_eq!(names.unwrap(), vec!["0", "1__optional"]);
+    assert_eq!(
+        names.unwrap(),
+        vec!["0", "1__0__optional", "1__1__optional", "1__2__optional"]
+    );
 }
 
 #[test]
 fn test_optional_generic_tuple() {
     let names = OptionalGenericTuple::<i32>::struct_reflection();
-    assert_eq!(names.unwrap(), vec!["0", "1__optional", "2__optional"]);
+    assert_eq!(
+        names.unwrap(),
+        vec!["0", "1__optional", "2__0__optional", "2__1__optional"]
+    );
 }
 
 #[test]
@@ -0,0 +1,432 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use struct_reflection::{SetFieldError, StructReflection};
+
+#[derive(StructReflection)]
+struct Address {
+    street: String,
+    number: u32,
+}
+
+#[derive(StructReflection)]
+struct User {
+    id: u64,
+    address: Address,
+    values: [(u8, i16); 2],
+    nickname: Option<String>,
+    manager: Option<Address>,
+    #[reflect(values)]
+    scores: HashMap<String, f32>,
+    tags: Vec<String>,
+    bio: Rc<str>,
+    visits: Mutex<u32>,
+}
+
+#[derive(StructReflection)]
+struct Pair<T> {
+    left: T,
+    right: T,
+}
+
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "union")]
+enum Event {
+    Click { x: i32, y: i32 },
+    Scroll { y: i32, delta: f32 },
+}
+
+#[derive(StructReflection)]
+enum Shape {
+    Circle { radius: f64 },
+    Empty,
+}
+
+fn user() -> User {
+    User {
+        id: 7,
+        address: Address {
+            street: "Main St".to_string(),
+            number: 12,
+        },
+        values: [(1, -1), (2, -2)],
+        nickname: Some("bob".to_string()),
+        manager: None,
+        scores: HashMap::from([("math".to_string(), 9.5)]),
+        tags: vec!["admin".to_string()],
+        bio: Rc::from("hello"),
+        visits: Mutex::new(3),
+    }
+}
+
+#[test]
+fn test_get_by_name() {
+    let user = user();
+
+    assert_eq!(user.struct_reflection_get_as::<u64>("id"), Some(&7));
+    assert_eq!(
+        user.struct_reflection_get_as::<String>("address__street")
+            .unwrap(),
+        "Main St"
+    );
+    assert_eq!(
+        user.struct_reflection_get_as::<i16>("values__1__1"),
+        Some(&-2)
+    );
+    assert_eq!(
+        user.struct_reflection_get_as::<String>("nickname__optional")
+            .unwrap(),
+        "bob"
+    );
+    assert_eq!(
+        user.struct_reflection_get_as::<f32>("scores__math"),
+        Some(&9.5)
+    );
+    assert_eq!(
+        user.struct_reflection_get_as::<Vec<String>>("tags")
+            .unwrap(),
+        &["admin"]
+    );
+
+    let any = user.struct_reflection_get("address__number").unwrap();
+    assert_eq!(any.downcast_ref::<u32>(), Some(&12));
+}
+
+#[test]
+fn test_get_missing_or_mistyped() {
+    let user = user();
+
+    // Unknown names, names of inner nodes, and options holding `None`
+    assert!(user.struct_reflection_get("nope").is_none());
+    assert!(user.struct_reflection_get("address").is_none());
+    assert!(user
+        .struct_reflection_get("manager__street__optional")
+        .is_none());
+
+    assert!(user.struct_reflection_get_as::<u32>("id").is_none());
+}
+
+#[test]
+fn test_leaves_that_cannot_be_walked_into() {
+    let user = user();
+
+    // Unsized contents are reached through their pointer, and locks are not
+    // walked into
+    let bio = user.struct_reflection_get_as::<Rc<str>>("bio").unwrap();
+    assert_eq!(&**bio, "hello");
+    let visits = user
+        .struct_reflection_get_as::<Mutex<u32>>("visits")
+        .unwrap();
+    assert_eq!(*visits.lock().unwrap(), 3);
+}
+
+#[test]
+fn test_values_follow_instance_names() {
+    let user = user();
+    let names: Vec<String> = user
+        .struct_reflection_values()
+        .unwrap()
+        .iter()
+        .map(|field| field.path.join(User::SEPARATOR))
+        .collect();
+
+    let mut expected = user.struct_reflection_instance().unwrap();
+    expected.retain(|name| !name.starts_with("manager__"));
+    assert_eq!(names, expected);
+}
+
+#[test]
+fn test_get_generic_and_enum_fields() {
+    let pair = Pair {
+        left: 'a',
+        right: 'b',
+    };
+    assert_eq!(pair.struct_reflection_get_as::<char>("right"), Some(&'b'));
+
+    let event = Event::Scroll { y: 4, delta: 0.5 };
+    assert_eq!(
+        event.struct_reflection_get_as::<&str>("variant"),
+        Some(&"Scroll")
+    );
+    assert_eq!(event.struct_reflection_get_as::<i32>("y"), Some(&4));
+    assert!(event.struct_reflection_get("x").is_none());
+
+    let shape = Shape::Circle { radius: 2.0 };
+    assert_eq!(
+        shape.struct_reflection_get_as::<f64>("Circle__radius"),
+        Some(&2.0)
+    );
+    assert!(Shape::Empty
+        .struct_reflection_get("Circle__radius")
+        .is_none());
+    assert!(Shape::Empty
+        .struct_reflection_get_as::<Shape>("Empty")
+        .is_some());
+}
+
+#[derive(StructReflection)]
+struct Limits {
+    cpu: u32,
+    memory: Option<u64>,
+    timeout: std::time::Duration,
+}
+
+#[derive(StructReflection)]
+struct Config {
+    name: String,
+    limits: Box<Limits>,
+    ports: [u16; 2],
+    #[reflect(values)]
+    weights: HashMap<String, f32>,
+    shape: Shape,
+    event: Event,
+}
+
+fn config() -> Config {
+    Config {
+        name: "default".to_string(),
+        limits: Box::new(Limits {
+            cpu: 1,
+            memory: Some(512),
+            timeout: std::time::Duration::from_secs(1),
+        }),
+        ports: [80, 443],
+        weights: HashMap::from([("a".to_string(), 1.0)]),
+        shape: Shape::Circle { radius: 1.0 },
+        event: Event::Click { x: 1, y: 2 },
+    }
+}
+
+#[test]
+fn test_set_from_str() {
+    let mut config = config();
+
+    config
+        .struct_reflection_set_from_str("limits__cpu", "4")
+        .unwrap();
+    config
+        .struct_reflection_set_from_str("limits__memory__optional", "1024")
+        .unwrap();
+    config
+        .struct_reflection_set_from_str("name", "prod")
+        .unwrap();
+    config
+        .struct_reflection_set_from_str("ports__1", "8443")
+        .unwrap();
+    config
+        .struct_reflection_set_from_str("weights__a", "0.5")
+        .unwrap();
+    config
+        .struct_reflection_set_from_str("shape__Circle__radius", "3")
+        .unwrap();
+    config
+        .struct_reflection_set_from_str("event__y", "-2")
+        .unwrap();
+
+    assert_eq!(config.limits.cpu, 4);
+    assert_eq!(config.limits.memory, Some(1024));
+    assert_eq!(config.name, "prod");
+    assert_eq!(config.ports, [80, 8443]);
+    assert_eq!(config.weights["a"], 0.5);
+    assert!(matches!(config.shape, Shape::Circle { radius } if radius == 3.0));
+    assert!(matches!(config.event, Event::Click { x: 1, y: -2 }));
+}
+
+#[test]
+fn test_set_and_get_mut() {
+    let mut config = config();
+
+    config
+        .struct_reflection_set("limits__timeout", std::time::Duration::from_secs(5))
+        .unwrap();
+    assert_eq!(config.limits.timeout.as_secs(), 5);
+
+    *config
+        .struct_reflection_get_mut_as::<u16>("ports__0")
+        .unwrap() += 1;
+    assert_eq!(config.ports[0], 81);
+
+    let names: Vec<String> = config
+        .struct_reflection_values_mut()
+        .unwrap()
+        .iter()
+        .map(|field| field.path.join(Config::SEPARATOR))
+        .collect();
+    assert!(names.contains(&"event__x".to_string()));
+    // The union tag cannot be assigned
+    assert!(!names.contains(&"event__variant".to_string()));
+}
+
+#[test]
+fn test_set_errors() {
+    let mut config = config();
+
+    let error = config
+        .struct_reflection_set_from_str("limits__gpu", "1")
+        .unwrap_err();
+    assert!(matches!(error, SetFieldError::UnknownField(ref name) if name == "limits__gpu"));
+    assert_eq!(error.to_string(), "no field named `limits__gpu`");
+
+    let error = config
+        .struct_reflection_set_from_str("limits__cpu", "four")
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        SetFieldError::Parse {
+            type_name: "u32",
+            ..
+        }
+    ));
+    assert_eq!(error.field(), "limits__cpu");
+    assert!(std::error::Error::source(&error).is_some());
+
+    let error = config
+        .struct_reflection_set_from_str("limits__timeout", "5s")
+        .unwrap_err();
+    assert!(matches!(error, SetFieldError::NotParsable { .. }));
+
+    let error = config
+        .struct_reflection_set("limits__cpu", 4u64)
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        SetFieldError::TypeMismatch {
+            expected: "u32",
+            found: "u64",
+            ..
+        }
+    ));
+
+    // Fields of other variants hold no value to assign
+    let error = config
+        .struct_reflection_set_from_str("event__delta", "1.5")
+        .unwrap_err();
+    assert!(matches!(error, SetFieldError::UnknownField(_)));
+    assert_eq!(config.limits.cpu, 1);
+}
+
+#[derive(StructReflection)]
+struct Labels<T> {
+    label: Option<u32>,
+    note: Option<T>,
+    #[reflect(option = "prefix")]
+    title: Option<String>,
+    owner: Option<Address>,
+}
+
+#[test]
+fn test_set_options_holding_none() {
+    let mut labels = Labels::<u8> {
+        label: None,
+        note: None,
+        title: None,
+        owner: None,
+    };
+
+    // Leaves of options holding `None` are set to `Some`
+    labels
+        .struct_reflection_set_from_str("label__optional", "4")
+        .unwrap();
+    labels
+        .struct_reflection_set_from_str("title__optional", "hello")
+        .unwrap();
+    labels.struct_reflection_set("note__optional", 2u8).unwrap();
+    assert_eq!(labels.label, Some(4));
+    assert_eq!(labels.title.as_deref(), Some("hello"));
+    assert_eq!(labels.note, Some(2));
+
+    let mut labels = Labels::<u8> {
+        label: None,
+        note: None,
+        title: None,
+        owner: None,
+    };
+    let error = labels
+        .struct_reflection_set_from_str("label__optional", "four")
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        SetFieldError::Parse {
+            type_name: "u32",
+            ..
+        }
+    ));
+    let error = labels
+        .struct_reflection_set("label__optional", 4u64)
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        SetFieldError::TypeMismatch {
+            expected: "u32",
+            ..
+        }
+    ));
+    // A generic leaf cannot be parsed
+    let error = labels
+        .struct_reflection_set_from_str("note__optional", "2")
+        .unwrap_err();
+    assert!(matches!(error, SetFieldError::NotParsable { .. }));
+    assert_eq!(labels.label, None);
+
+    // A struct cannot be built from one of its fields
+    let error = labels
+        .struct_reflection_set_from_str("owner__number__optional", "1")
+        .unwrap_err();
+    assert!(matches!(error, SetFieldError::UnknownField(_)));
+}
+
+#[derive(StructReflection)]
+struct Collapsed {
+    #[reflect(option = "collapse")]
+    count: Option<u32>,
+    #[reflect(option = "collapse")]
+    owner: Option<Address>,
+}
+
+#[test]
+fn test_set_collapsed_options() {
+    let mut collapsed = Collapsed {
+        count: Some(3),
+        owner: None,
+    };
+
+    // Mutable reads borrow the value the option holds, like reads
+    assert_eq!(
+        collapsed.struct_reflection_get_as::<u32>("count__optional"),
+        Some(&3)
+    );
+    *collapsed
+        .struct_reflection_get_mut_as::<u32>("count__optional")
+        .unwrap() = 5;
+    assert_eq!(collapsed.count, Some(5));
+
+    collapsed
+        .struct_reflection_set_from_str("count__optional", "9")
+        .unwrap();
+    assert_eq!(collapsed.count, Some(9));
+
+    // Assigning an option holding `None` sets it to `Some`
+    collapsed.count = None;
+    collapsed
+        .struct_reflection_set_from_str("count__optional", "7")
+        .unwrap();
+    assert_eq!(collapsed.count, Some(7));
+
+    collapsed
+        .struct_reflection_set(
+            "owner__optional",
+            Address {
+                street: "Main".to_string(),
+                number: 1,
+            },
+        )
+        .unwrap();
+    assert_eq!(collapsed.owner.as_ref().map(|owner| owner.number), Some(1));
+    let error = collapsed
+        .struct_reflection_set_from_str("owner__optional", "Main 1")
+        .unwrap_err();
+    assert!(matches!(error, SetFieldError::NotParsable { .. }));
+}
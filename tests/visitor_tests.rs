@@ -0,0 +1,405 @@
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use struct_reflection::{
+    FieldInfo, FieldKey, FieldPath, FieldVisitor, Leaf, OptionStyle, PrimitiveKind, Segment,
+    StructReflection,
+};
+
+#[derive(StructReflection)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(StructReflection)]
+struct Meters(f64);
+
+#[derive(StructReflection)]
+#[reflect(transparent)]
+struct Id(u64);
+
+#[derive(StructReflection)]
+#[reflect(enum_strategy = "union")]
+enum Motion {
+    Move { dx: i32, dy: i32 },
+    Turn { dy: i32, angle: f32 },
+}
+
+#[derive(StructReflection)]
+enum Marker {
+    Pin { at: Point },
+    Blank,
+}
+
+#[derive(StructReflection)]
+struct Scene<T> {
+    id: Id,
+    origin: Point,
+    corners: [(u8, i16); 2],
+    target: Option<Point>,
+    goal: Option<Point>,
+    #[reflect(option = "prefix")]
+    previous: Option<(u8, Point)>,
+    #[reflect(prefix = "bill")]
+    billing: Point,
+    #[reflect(prefix = "n")]
+    count: Option<u32>,
+    #[reflect(option = "collapse")]
+    scale: Option<Meters>,
+    code: T,
+    #[reflect(flatten)]
+    center: Point,
+    motion: Motion,
+    marker: Marker,
+    #[reflect(elements)]
+    path: Vec<Point>,
+    #[reflect(values)]
+    weights: BTreeMap<String, f32>,
+    boxed: Box<Point>,
+    title: Rc<str>,
+    #[reflect(skip)]
+    cache: Vec<u8>,
+}
+
+fn scene() -> Scene<char> {
+    Scene {
+        id: Id(9),
+        origin: Point { x: 1, y: 2 },
+        corners: [(1, -1), (2, -2)],
+        target: None,
+        goal: Some(Point { x: 12, y: 13 }),
+        previous: Some((1, Point { x: 14, y: 15 })),
+        billing: Point { x: 16, y: 17 },
+        count: Some(18),
+        scale: Some(Meters(2.5)),
+        code: 'c',
+        center: Point { x: 3, y: 4 },
+        motion: Motion::Turn { dy: 5, angle: 0.5 },
+        marker: Marker::Pin {
+            at: Point { x: 6, y: 7 },
+        },
+        path: vec![Point { x: 8, y: 9 }],
+        weights: BTreeMap::from([("a".to_string(), 1.0), ("b".to_string(), 2.0)]),
+        boxed: Box::new(Point { x: 10, y: 11 }),
+        title: Rc::from("hello"),
+        cache: Vec::new(),
+    }
+}
+
+/// Rebuilds the path of every leaf from the keys of the nodes above it.
+#[derive(Default)]
+struct Paths {
+    stack: Vec<Segment>,
+    /// Positions in `stack` of the contents of `Suffix` options, whose marker
+    /// goes at the end of the path.
+    suffixed: Vec<usize>,
+    leaves: Vec<(FieldPath, &'static str, PrimitiveKind, bool)>,
+}
+
+impl Paths {
+    fn enter(&mut self, key: FieldKey<'_>) {
+        self.stack.push(key.to_segment());
+    }
+
+    fn paths(&self) -> Vec<FieldPath> {
+        self.leaves.iter().map(|leaf| leaf.0.clone()).collect()
+    }
+}
+
+impl FieldVisitor for Paths {
+    fn enter_struct(&mut self, key: FieldKey<'_>, _type_name: &'static str) {
+        self.enter(key);
+    }
+
+    fn enter_array(&mut self, key: FieldKey<'_>, _len: Option<usize>) {
+        self.enter(key);
+    }
+
+    fn enter_tuple(&mut self, key: FieldKey<'_>, _len: usize) {
+        self.enter(key);
+    }
+
+    fn enter_option(&mut self, key: FieldKey<'_>, style: OptionStyle) {
+        self.enter(key);
+        if style == OptionStyle::Suffix {
+            self.suffixed.push(self.stack.len());
+        }
+    }
+
+    fn enter_map(&mut self, key: FieldKey<'_>, _len: Option<usize>) {
+        self.enter(key);
+    }
+
+    fn leaf(&mut self, key: FieldKey<'_>, leaf: Leaf<'_>) {
+        let mut segments = self.stack.clone();
+        segments.push(key.to_segment());
+        for &index in self.suffixed.iter().rev() {
+            assert_eq!(segments.remove(index), Segment::OptionalMarker);
+        }
+        segments.extend(self.suffixed.iter().map(|_| Segment::OptionalMarker));
+        self.leaves.push((
+            FieldPath::from(segments),
+            leaf.type_name,
+            leaf.kind,
+            leaf.is_generic,
+        ));
+    }
+
+    fn exit(&mut self) {
+        self.stack.pop();
+        if self.suffixed.last() > Some(&self.stack.len()) {
+            self.suffixed.pop();
+        }
+    }
+}
+
+/// Records every call, one line each.
+#[derive(Default)]
+struct Events(Vec<String>);
+
+impl FieldVisitor for Events {
+    fn enter_struct(&mut self, key: FieldKey<'_>, type_name: &'static str) {
+        self.0.push(format!("struct {key:?} {type_name}"));
+    }
+
+    fn enter_array(&mut self, key: FieldKey<'_>, len: Option<usize>) {
+        self.0.push(format!("array {key:?} {len:?}"));
+    }
+
+    fn enter_tuple(&mut self, key: FieldKey<'_>, len: usize) {
+        self.0.push(format!("tuple {key:?} {len}"));
+    }
+
+    fn enter_option(&mut self, key: FieldKey<'_>, style: OptionStyle) {
+        self.0.push(format!("option {key:?} {style:?}"));
+    }
+
+    fn enter_map(&mut self, key: FieldKey<'_>, len: Option<usize>) {
+        self.0.push(format!("map {key:?} {len:?}"));
+    }
+
+    fn leaf(&mut self, key: FieldKey<'_>, leaf: Leaf<'_>) {
+        match leaf.value {
+            Some(value) => match value.downcast_ref::<i32>() {
+                Some(value) => self.0.push(format!("leaf {key:?} = {value}")),
+                None => self.0.push(format!("leaf {key:?} {}", leaf.type_name)),
+            },
+            None => self.0.push(format!("leaf {key:?} {}", leaf.type_name)),
+        }
+    }
+
+    fn exit(&mut self) {
+        self.0.push("exit".to_string());
+    }
+}
+
+#[derive(StructReflection)]
+struct Small {
+    origin: Point,
+    pairs: [(u8, bool); 1],
+    target: Option<Point>,
+}
+
+#[test]
+fn test_type_walk_events() {
+    let mut events = Events::default();
+    Small::struct_reflection_visit(&mut events);
+
+    let point = std::any::type_name::<Point>();
+    assert_eq!(
+        events.0,
+        [
+            format!("struct Named(\"origin\") {point}"),
+            "leaf Named(\"x\") i32".to_string(),
+            "leaf Named(\"y\") i32".to_string(),
+            "exit".to_string(),
+            "array Named(\"pairs\") Some(1)".to_string(),
+            "tuple ArrayIndex(0) 2".to_string(),
+            "leaf TupleIndex(0) u8".to_string(),
+            "leaf TupleIndex(1) bool".to_string(),
+            "exit".to_string(),
+            "exit".to_string(),
+            "option Named(\"target\") Suffix".to_string(),
+            format!("struct Optional {point}"),
+            "leaf Named(\"x\") i32".to_string(),
+            "leaf Named(\"y\") i32".to_string(),
+            "exit".to_string(),
+            "exit".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_type_walk_follows_fields() {
+    let mut paths = Paths::default();
+    Scene::<char>::struct_reflection_visit(&mut paths);
+
+    // With the markers of `Suffix` options moved to the end, the paths are
+    // those of the names
+    assert_eq!(
+        paths.paths(),
+        Scene::<char>::struct_reflection_paths().unwrap()
+    );
+
+    let fields: Vec<FieldInfo> = Scene::<char>::struct_reflection_fields().unwrap();
+    for ((path, type_name, kind, is_generic), field) in paths.leaves.iter().zip(&fields) {
+        assert_eq!(path, &field.path);
+        assert_eq!(*type_name, field.type_name, "{path}");
+        assert_eq!(*kind, field.kind, "{path}");
+        assert_eq!(*is_generic, field.is_generic, "{path}");
+    }
+}
+
+#[test]
+fn test_value_walk_follows_values() {
+    let scene = scene();
+    let mut paths = Paths::default();
+    scene.struct_reflection_visit_values(&mut paths);
+
+    let values = scene.struct_reflection_values().unwrap();
+    let expected: Vec<FieldPath> = values.iter().map(|field| field.path.clone()).collect();
+    assert_eq!(paths.paths(), expected);
+
+    let names: Vec<String> = paths
+        .paths()
+        .iter()
+        .map(|path| path.join(Scene::<char>::SEPARATOR))
+        .collect();
+    // `target` holds `None`, and only the current variants are walked
+    assert!(!names.iter().any(|name| name.starts_with("target")));
+    assert!(names.contains(&"scale__optional".to_string()));
+    assert!(names.contains(&"goal__x__optional".to_string()));
+    assert!(names.contains(&"previous__optional__1__y".to_string()));
+    assert!(names.contains(&"bill__x".to_string()));
+    assert!(names.contains(&"count__optional".to_string()));
+    assert!(names.contains(&"motion__angle".to_string()));
+    assert!(!names.contains(&"motion__dx".to_string()));
+    assert!(names.contains(&"marker__Pin__at__x".to_string()));
+    assert!(names.contains(&"path__0__y".to_string()));
+    assert!(names.contains(&"weights__b".to_string()));
+}
+
+#[test]
+fn test_value_walk_borrows_leaves() {
+    let scene = scene();
+    let mut events = Events::default();
+    scene.struct_reflection_visit_values(&mut events);
+
+    let events = events.0;
+    let at = |event: &str| events.iter().position(|e| e == event).unwrap();
+
+    // Entries are entered in place of the values they hold
+    assert!(at("struct Named(\"origin\") visitor_tests::Point") < at("leaf Named(\"x\") = 1"));
+    assert!(events.contains(&"leaf Named(\"x\") = 3".to_string()));
+    assert!(events.contains(&"array Named(\"path\") Some(1)".to_string()));
+    assert!(events.contains(&"map Named(\"weights\") Some(2)".to_string()));
+    assert!(events.contains(&"leaf Key(\"a\") f32".to_string()));
+    assert!(events.contains(&"leaf Named(\"variant\") visitor_tests::Motion".to_string()));
+    assert!(events.contains(&"leaf Named(\"title\") alloc::rc::Rc<str>".to_string()));
+    // Prefixes stand for fields with fields of their own only
+    assert!(events.contains(&"struct Named(\"bill\") visitor_tests::Point".to_string()));
+    assert!(events.contains(&"option Named(\"count\") Suffix".to_string()));
+    assert!(events.contains(&"option Named(\"previous\") Prefix".to_string()));
+    // Collapsed options hold a leaf of the type of their value
+    let scale = at("option Named(\"scale\") Collapse");
+    assert_eq!(events[scale + 1], "leaf Optional visitor_tests::Meters");
+    // Options holding `None` have no contents
+    let target = at("option Named(\"target\") Suffix");
+    assert_eq!(events[target + 1], "exit");
+}
+
+#[test]
+fn test_value_walk_leaf_values() {
+    #[derive(Default)]
+    struct Strings(Vec<String>);
+
+    impl FieldVisitor for Strings {
+        fn leaf(&mut self, _key: FieldKey<'_>, leaf: Leaf<'_>) {
+            let Some(value) = leaf.value else { return };
+            if let Some(value) = value.downcast_ref::<Rc<str>>() {
+                self.0.push(value.to_string());
+            } else if let Some(value) = value.downcast_ref::<&'static str>() {
+                self.0.push(value.to_string());
+            }
+        }
+    }
+
+    let mut strings = Strings::default();
+    scene().struct_reflection_visit_values(&mut strings);
+    assert_eq!(strings.0, ["Turn", "hello"]);
+}
+
+type Maybe<T> = Option<T>;
+
+#[test]
+fn test_aliased_option_leaf() {
+    #[derive(StructReflection)]
+    struct Aliased {
+        level: Maybe<i32>,
+    }
+
+    let mut events = Events::default();
+    Aliased::struct_reflection_visit(&mut events);
+    assert!(events.0.contains(&"leaf Optional i32".to_string()));
+
+    let mut events = Events::default();
+    Aliased { level: Some(3) }.struct_reflection_visit_values(&mut events);
+    assert!(events.0.contains(&"leaf Optional = 3".to_string()));
+}
+
+#[derive(StructReflection)]
+struct Node {
+    value: i32,
+    next: Option<Box<Node>>,
+}
+
+#[test]
+fn test_recursive_visit() {
+    let mut events = Events::default();
+    Node::struct_reflection_visit(&mut events);
+
+    let node = std::any::type_name::<Node>();
+    assert_eq!(
+        events.0,
+        [
+            "leaf Named(\"value\") i32".to_string(),
+            "option Named(\"next\") Suffix".to_string(),
+            format!("struct Optional {node}"),
+            format!("leaf Ref(\"Node\") {node}"),
+            "exit".to_string(),
+            "exit".to_string(),
+        ]
+    );
+
+    // Values are finite, and walked all the way down
+    let list = Node {
+        value: 1,
+        next: Some(Box::new(Node {
+            value: 2,
+            next: None,
+        })),
+    };
+    let mut events = Events::default();
+    list.struct_reflection_visit_values(&mut events);
+    assert!(events.0.contains(&"leaf Named(\"value\") = 2".to_string()));
+}
+
+#[test]
+fn test_flattened_fields_without_names() {
+    #[derive(StructReflection)]
+    struct Flattened {
+        #[reflect(flatten)]
+        pair: (u8, u8),
+        #[reflect(flatten)]
+        count: Option<u16>,
+        #[reflect(flatten)]
+        origin: Point,
+    }
+
+    let mut paths = Paths::default();
+    Flattened::struct_reflection_visit(&mut paths);
+    assert_eq!(paths.paths(), Flattened::struct_reflection_paths().unwrap());
+}
@@ -0,0 +1,163 @@
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, RwLock};
+
+use struct_reflection::{PrimitiveKind, StructReflection};
+
+#[derive(StructReflection, Clone)]
+struct Address {
+    street: String,
+    city: String,
+}
+
+#[derive(StructReflection)]
+struct Wrapped {
+    boxed: Box<Address>,
+    shared: Rc<Address>,
+    synced: Arc<Address>,
+    borrowed: Cow<'static, Address>,
+    cell: Cell<[Address; 1]>,
+    ref_cell: RefCell<Address>,
+    mutex: Mutex<Address>,
+    lock: RwLock<Address>,
+}
+
+#[derive(StructReflection)]
+#[reflect(transparent)]
+struct UserId(u64);
+
+#[derive(StructReflection)]
+#[reflect(transparent)]
+struct Home {
+    #[reflect(skip)]
+    id: u64,
+    address: Address,
+}
+
+#[derive(StructReflection)]
+#[reflect(transparent)]
+struct Tagged<T> {
+    value: T,
+    #[reflect(skip)]
+    marker: PhantomData<T>,
+}
+
+#[derive(StructReflection)]
+struct Account {
+    id: UserId,
+    home: Home,
+    score: Tagged<f32>,
+    #[reflect(values)]
+    addresses: std::collections::BTreeMap<String, Box<Address>>,
+}
+
+fn address() -> Address {
+    Address {
+        street: "Main".to_string(),
+        city: "Springfield".to_string(),
+    }
+}
+
+#[test]
+fn test_wrappers_are_transparent() {
+    let names = Wrapped::struct_reflection().unwrap();
+    let expected: Vec<String> = [
+        "boxed", "shared", "synced", "borrowed", "cell__0", "ref_cell", "mutex", "lock",
+    ]
+    .iter()
+    .flat_map(|field| [format!("{field}__street"), format!("{field}__city")])
+    .collect();
+    assert_eq!(names, expected);
+    assert_eq!(
+        Box::<Address>::struct_reflection().unwrap(),
+        Address::struct_reflection().unwrap()
+    );
+}
+
+#[test]
+fn test_wrapper_instances() {
+    let wrapped = Wrapped {
+        boxed: Box::new(address()),
+        shared: Rc::new(address()),
+        synced: Arc::new(address()),
+        borrowed: Cow::Owned(address()),
+        cell: Cell::new([address()]),
+        ref_cell: RefCell::new(address()),
+        mutex: Mutex::new(address()),
+        lock: RwLock::new(address()),
+    };
+
+    let _guard = wrapped.mutex.lock().unwrap();
+    let _borrow = wrapped.ref_cell.borrow_mut();
+    assert_eq!(
+        wrapped.struct_reflection_instance().unwrap(),
+        Wrapped::struct_reflection().unwrap()
+    );
+}
+
+#[test]
+fn test_transparent_newtypes() {
+    assert_eq!(UserId::struct_reflection().unwrap(), vec![""]);
+    assert_eq!(Home::struct_reflection().unwrap(), vec!["street", "city"]);
+    assert_eq!(
+        Account::struct_reflection().unwrap(),
+        vec![
+            "id",
+            "home__street",
+            "home__city",
+            "score",
+            "addresses__{}__street",
+            "addresses__{}__city"
+        ]
+    );
+
+    let fields = Account::struct_reflection_fields().unwrap();
+    assert_eq!(fields[0].type_name, "u64");
+    assert_eq!(fields[0].kind, PrimitiveKind::Int);
+    assert!(fields[3].is_generic);
+}
+
+#[derive(StructReflection)]
+struct BoxedLeaves {
+    count: Box<u32>,
+    label: Box<str>,
+    bytes: Box<[u8; 2]>,
+    limit: Option<Box<u32>>,
+}
+
+#[test]
+fn test_boxed_leaves() {
+    // Fields reflect what their boxes hold, though only boxes of
+    // `StructReflection` types are `StructReflection` types themselves
+    assert_eq!(
+        BoxedLeaves::struct_reflection().unwrap(),
+        ["count", "label", "bytes__0", "bytes__1", "limit__optional"]
+    );
+    assert_eq!(BoxedLeaves::FIELD_COUNT, 5);
+    let fields = BoxedLeaves::struct_reflection_fields().unwrap();
+    assert_eq!(fields[0].kind, PrimitiveKind::Int);
+
+    let mut leaves = BoxedLeaves {
+        count: Box::new(1),
+        label: "a".into(),
+        bytes: Box::new([4, 5]),
+        limit: Some(Box::new(2)),
+    };
+    assert_eq!(leaves.struct_reflection_get_as::<u32>("count"), Some(&1));
+    assert_eq!(
+        leaves
+            .struct_reflection_get_as::<Box<str>>("label")
+            .map(|label| &**label),
+        Some("a")
+    );
+    leaves.struct_reflection_set_from_str("count", "3").unwrap();
+    assert_eq!(*leaves.count, 3);
+    assert_eq!(
+        leaves.struct_reflection_get_as::<u32>("limit__optional"),
+        Some(&2)
+    );
+}